@@ -0,0 +1,419 @@
+//! interface en ligne de commande du parser FAT32
+//! 
+//! simple consommateur de la bibliothèque `fat32-parser` avec la
+//! feature `std`.
+
+use fat32_parser::devices::file::FileDevice;
+use fat32_parser::utils::helpers::short_name_to_string;
+
+// point d'entrée du binaire
+fn main() {
+    use std::env;
+    use std::process;
+    
+    let args: Vec<String> = env::args().collect();
+    
+    if args.len() < 2 {
+        eprintln!("Usage: {} <image.fat32> [secteur_début_partition]", args[0]);
+        eprintln!("   ou: {} generate-img", args[0]);
+        eprintln!("   ou: {} carve <image.fat32> <dossier>", args[0]);
+        eprintln!("   ou: {} slack <image.fat32>", args[0]);
+        process::exit(1);
+    }
+    
+    let arg = &args[1];
+    
+    // récupérer des fichiers dans les clusters libres
+    if arg == "carve" {
+        if args.len() < 4 {
+            eprintln!("Usage: {} carve <image.fat32> <dossier>", args[0]);
+            process::exit(1);
+        }
+        if let Err(e) = carve_image(&args[2], &args[3]) {
+            eprintln!("\nErreur: {}", e);
+            process::exit(1);
+        }
+        return;
+    }
+    
+    // générer une image de test
+    if arg == "generate-img" {
+        match generate_test_image("test.img") {
+            Ok(_) => {
+                println!("✓ Image test.img générée avec succès");
+                println!("\nTestez avec: cargo run test.img");
+            }
+            Err(e) => {
+                eprintln!("Erreur: {}", e);
+                process::exit(1);
+            }
+        }
+        return;
+    }
+    
+    // extraire le slack des fichiers et les emplacements inutilisés
+    if arg == "slack" {
+        if args.len() < 3 {
+            eprintln!("Usage: {} slack <image.fat32>", args[0]);
+            process::exit(1);
+        }
+        if let Err(e) = dump_slack(&args[2]) {
+            eprintln!("\nErreur: {}", e);
+            process::exit(1);
+        }
+        return;
+    }
+    
+    // parser une image existante
+    println!("\n=== PARSER FAT32 ===\n");
+    println!("Image: {}", arg);
+    
+    let partition_start = match args.get(2).map(|s| s.parse::<u64>()) {
+        None => 0,
+        Some(Ok(start)) => start,
+        Some(Err(_)) => {
+            eprintln!("Secteur de début de partition invalide: {}", args[2]);
+            process::exit(1);
+        }
+    };
+    
+    match parse_fat32_image(arg, partition_start) {
+        Ok(_) => {
+            println!("\nParsing réussi !");
+        }
+        Err(e) => {
+            eprintln!("\nErreur: {}", e);
+            process::exit(1);
+        }
+    }
+}
+
+fn parse_fat32_image(path: &str, partition_start: u64) -> Result<(), Box<dyn std::error::Error>> {
+    use fat32_parser::operations::directory::Visit;
+    use fat32_parser::operations::parser::Fat32Parser;
+    use fat32_parser::utils::validator::{MountOptions, Severity};
+    
+    println!("Ouverture de l'image...\n");
+    let device = FileDevice::new(path).map_err(|e| format!("Erreur lors de l'ouverture de {}: {}", path, e))?;
+    
+    // mode tolérant : on inspecte aussi les volumes incohérents
+    println!("Lecture du boot sector...");
+    let parser = Fat32Parser::mount_at(device, partition_start, MountOptions::lenient())
+        .map_err(|e| format!("Erreur lors du parsing du boot sector de {}: {}", path, e))?;
+    
+    for problem in parser.validation().problems() {
+        let marker = match problem.severity() {
+            Severity::Warning => "⚠",
+            Severity::Error | Severity::Fatal => "✗",
+        };
+        println!("  {} {}", marker, problem);
+    }
+    
+    let boot = parser.boot_sector();
+    let signature = boot.signature;
+    let bytes_per_sector = boot.bytes_per_sector;
+    let sectors_per_cluster = boot.sectors_per_cluster;
+    let num_fats = boot.num_fats;
+    let fat_size = boot.fat_size_32;
+    let total_sectors = boot.total_sectors_32;
+    let root_cluster = boot.root_cluster;
+    let oem_name = boot.oem_name;
+    let volume_label = boot.volume_label;
+    let hidden_sectors = boot.hidden_sectors;
+    
+    println!("\nBOOT SECTOR:");
+    println!("  ├─ Début partition: secteur {} (secteurs cachés: {})", partition_start, hidden_sectors);
+    println!("  ├─ Signature: 0x{:04X} {}", signature,
+        if boot.is_valid() { "✓" } else { "✗" });
+    println!("  ├─ OEM: {:?}", std::str::from_utf8(&oem_name).unwrap_or("???"));
+    println!("  ├─ Octets/secteur: {}", bytes_per_sector);
+    println!("  ├─ Secteurs/cluster: {}", sectors_per_cluster);
+    println!("  ├─ Taille cluster: {} octets", boot.cluster_size());
+    println!("  ├─ Nombre de FAT: {}", num_fats);
+    println!("  ├─ Taille FAT: {} secteurs", fat_size);
+    println!("  ├─ Total secteurs: {}", total_sectors);
+    let geometry = parser.geometry();
+    println!("  ├─ Clusters de données: {} ({:?})", geometry.cluster_count, geometry.fat_type());
+    println!("  ├─ Cluster racine: {}", root_cluster);
+    println!("  ├─ Volume: {:?}",
+        std::str::from_utf8(&volume_label).unwrap_or("???").trim());
+    
+    let flags = parser.volume_flags();
+    println!("  └─ État: {}{}",
+        if flags.clean_shutdown { "démonté proprement" } else { "non démonté proprement" },
+        if flags.hard_error { ", erreur matérielle signalée" } else { "" });
+    if parser.needs_check() {
+        println!("\n⚠ le volume devrait être vérifié avant d'être modifié");
+    }
+    
+    println!("\nLecture du répertoire racine...");
+    let entries = parser.read_root_dir()
+        .map_err(|e| format!("Erreur lecture du répertoire racine de {}: {}", path, e))?;
+    
+    println!("\nCONTENU:\n");
+    
+    let mut count = 0;
+    for entry in entries.iter() {
+        if !entry.is_empty() && !entry.is_long_name() {
+            count += 1;
+            let name = short_name_to_string(&entry.name);
+            let name_str = std::str::from_utf8(&name).unwrap_or("???")
+                .trim_end_matches('\0').trim();
+            
+            if entry.is_directory() {
+                println!("{} (cluster: {})", name_str, entry.first_cluster());
+            } else {
+                let size = entry.file_size;
+                println!("{} ({} octets, cluster: {})",
+                    name_str, size, entry.first_cluster());
+            }
+        }
+    }
+    
+    println!("\n✓ {} éléments trouvés", count);
+    
+    if let Some(available) = parser.device_sectors().filter(|_| parser.is_truncated()) {
+        println!("\nIMAGE TRONQUÉE: {} secteurs présents sur {}\n", available, total_sectors);
+        parser.for_each_truncated(|truncated| {
+            let name = short_name_to_string(&truncated.entry.name);
+            let name_str = std::str::from_utf8(&name).unwrap_or("???").trim_end_matches('\0');
+            if truncated.entry.is_directory() {
+                println!("{}{} (répertoire non parcouru)", "  ".repeat(truncated.depth), name_str);
+            } else {
+                println!("{}{} ({} octets lisibles sur {})", "  ".repeat(truncated.depth),
+                    name_str, truncated.readable, truncated.entry.file_size);
+            }
+            Ok(Visit::Continue)
+        }).map_err(|e| format!("Erreur lors du parcours de {}: {}", path, e))?;
+    }
+    
+    Ok(())
+}
+
+fn carve_image(path: &str, output_dir: &str) -> Result<(), Box<dyn std::error::Error>> {
+    use std::io::Write;
+    use fat32_parser::operations::carving::CarvingOptions;
+    use fat32_parser::operations::directory::Visit;
+    use fat32_parser::operations::parser::Fat32Parser;
+    use fat32_parser::utils::validator::MountOptions;
+    
+    println!("\n=== CARVING FAT32 ===\n");
+    println!("Image: {}", path);
+    
+    // mode tolérant : une image tronquée est lue jusqu'à sa fin
+    let device = FileDevice::new(path).map_err(|e| format!("Erreur lors de l'ouverture de {}: {}", path, e))?;
+    let parser = Fat32Parser::mount_at(device, 0, MountOptions::lenient())
+        .map_err(|e| format!("Erreur lors du parsing du boot sector de {}: {}", path, e))?;
+    
+    std::fs::create_dir_all(output_dir)?;
+    
+    let mut count = 0;
+    let mut buffer = vec![0u8; 64 * 1024];
+    let mut output_error = None;
+    parser.carve(CarvingOptions::default(), |carved| {
+        let name = format!("{}/carved_{}.{}", output_dir, carved.start_cluster, carved.kind.extension());
+        println!("{} ({} octets{}, cluster: {})", name, carved.length,
+            if carved.length_known { "" } else { ", taille estimée" }, carved.start_cluster);
+        
+        let mut file = match std::fs::File::create(&name) {
+            Ok(file) => file,
+            Err(e) => {
+                output_error = Some(format!("{}: {}", name, e));
+                return Ok(Visit::Stop);
+            }
+        };
+        let mut offset = 0;
+        while offset < carved.length {
+            let read = parser.read_carved(carved, offset, &mut buffer)?;
+            if let Err(e) = file.write_all(&buffer[..read]) {
+                output_error = Some(format!("{}: {}", name, e));
+                return Ok(Visit::Stop);
+            }
+            offset += read as u32;
+        }
+        
+        count += 1;
+        Ok(Visit::Continue)
+    }).map_err(|e| format!("Erreur lors du carving de {}: {}", path, e))?;
+    if let Some(e) = output_error {
+        return Err(e.into());
+    }
+    
+    println!("\n✓ {} fichiers récupérés", count);
+    
+    Ok(())
+}
+
+fn dump_slack(path: &str) -> Result<(), Box<dyn std::error::Error>> {
+    use fat32_parser::operations::directory::Visit;
+    use fat32_parser::operations::parser::Fat32Parser;
+    use fat32_parser::utils::validator::MountOptions;
+    
+    // affiche des octets en hexadécimal, 16 par ligne
+    fn hexdump(data: &[u8], base: usize) {
+        for (i, line) in data.chunks(16).enumerate() {
+            let hex: Vec<String> = line.iter().map(|b| format!("{:02X}", b)).collect();
+            let ascii: String = line.iter()
+                .map(|&b| if b.is_ascii_graphic() || b == b' ' { b as char } else { '.' })
+                .collect();
+            println!("      {:04X}  {:<47}  {}", base + i * 16, hex.join(" "), ascii);
+        }
+    }
+    
+    println!("\n=== SLACK FAT32 ===\n");
+    println!("Image: {}", path);
+    
+    // mode tolérant : une image tronquée est lue jusqu'à sa fin
+    let device = FileDevice::new(path).map_err(|e| format!("Erreur lors de l'ouverture de {}: {}", path, e))?;
+    let parser = Fat32Parser::mount_at(device, 0, MountOptions::lenient())
+        .map_err(|e| format!("Erreur lors du parsing du boot sector de {}: {}", path, e))?;
+    
+    println!("\nSLACK DES FICHIERS:\n");
+    parser.for_each_file_slack(|region| {
+        let name = short_name_to_string(&region.entry.name);
+        let name_str = std::str::from_utf8(&name).unwrap_or("???").trim_end_matches('\0');
+        
+        println!("{} (cluster: {}, secteur: {}, offset: {}, {} octets)",
+            name_str, region.cluster, region.sector, region.offset, region.data.len());
+        if region.data.iter().all(|&b| b == 0) {
+            println!("      (zéros)");
+        } else {
+            hexdump(region.data, region.offset);
+        }
+        Ok(Visit::Continue)
+    }).map_err(|e| format!("Erreur lecture slack de {}: {}", path, e))?;
+    
+    println!("\nEMPLACEMENTS DE RÉPERTOIRE INUTILISÉS:\n");
+    parser.for_each_unused_dir_slot(|slot| {
+        println!("{} (cluster: {}, secteur: {}, entrée: {})",
+            if slot.after_end { "après la fin" } else { "supprimé" },
+            slot.location.cluster, slot.location.sector, slot.location.index);
+        hexdump(&slot.raw, slot.location.index * 32);
+        Ok(Visit::Continue)
+    }).map_err(|e| format!("Erreur lecture des répertoires de {}: {}", path, e))?;
+    
+    Ok(())
+}
+
+fn generate_test_image(path: &str) -> Result<(), Box<dyn std::error::Error>> {
+    use fat32_parser::block_device::BlockDeviceMut;
+    use fat32_parser::devices::ram::RamBlockDevice;
+    use fat32_parser::structures::boot_sector::BootSector;
+    use fat32_parser::structures::dir_entry::DirEntry;
+    
+    println!("Génération de l'image FAT32...\n");
+    
+    // créer boot sector
+    let boot_sector = BootSector {
+        jmp_boot: [0xEB, 0x58, 0x90],
+        oem_name: *b"MSWIN4.1",
+        bytes_per_sector: 512,
+        sectors_per_cluster: 8,
+        reserved_sector_count: 32,
+        num_fats: 2,
+        root_entry_count: 0,
+        total_sectors_16: 0,
+        media_type: 0xF8,
+        fat_size_16: 0,
+        sectors_per_track: 63,
+        num_heads: 255,
+        hidden_sectors: 0,
+        total_sectors_32: 1024000,
+        fat_size_32: 1000,
+        ext_flags: 0,
+        fs_version: 0,
+        root_cluster: 2,
+        fs_info_sector: 1,
+        backup_boot_sector: 6,
+        reserved: [0; 12],
+        drive_number: 0x80,
+        reserved1: 0,
+        boot_signature: 0x29,
+        volume_id: 0x12345678,
+        volume_label: *b"TEST VOL   ",
+        fs_type: *b"FAT32   ",
+        boot_code: [0; 420],
+        signature: 0xAA55,
+    };
+    
+    // fichiers de test
+    let file1 = DirEntry {
+        name: *b"README  TXT",
+        attributes: 0x20,
+        nt_reserved: 0,
+        creation_time_tenth: 0,
+        creation_time: 0,
+        creation_date: 0,
+        last_access_date: 0,
+        first_cluster_high: 0,
+        last_write_time: 0,
+        last_write_date: 0,
+        first_cluster_low: 3,
+        file_size: 1024,
+    };
+    
+    let file2 = DirEntry {
+        name: *b"DATA    BIN",
+        attributes: 0x20,
+        nt_reserved: 0,
+        creation_time_tenth: 0,
+        creation_time: 0,
+        creation_date: 0,
+        last_access_date: 0,
+        first_cluster_high: 0,
+        last_write_time: 0,
+        last_write_date: 0,
+        first_cluster_low: 4,
+        file_size: 2048,
+    };
+    
+    let dir1 = DirEntry {
+        name: *b"DOCS       ",
+        attributes: 0x10,
+        nt_reserved: 0,
+        creation_time_tenth: 0,
+        creation_time: 0,
+        creation_date: 0,
+        last_access_date: 0,
+        first_cluster_high: 0,
+        last_write_time: 0,
+        last_write_date: 0,
+        first_cluster_low: 5,
+        file_size: 0,
+    };
+    
+    // l'image grandit jusqu'au dernier secteur écrit : elle s'arrête après
+    // le cluster racine, bien avant la fin annoncée du volume
+    let mut device = RamBlockDevice::new();
+    
+    // écrire boot sector
+    device.write_sector(0, &boot_sector.to_bytes())?;
+    
+    // premier secteur de FAT : entrées réservées, racine et fichiers en fin de chaîne
+    let mut fat_sector = [0u8; 512];
+    for cluster in 0..6 {
+        let value: u32 = if cluster == 0 { 0x0FFFFFF8 } else { 0x0FFFFFFF };
+        fat_sector[cluster * 4..cluster * 4 + 4].copy_from_slice(&value.to_le_bytes());
+    }
+    let geometry = boot_sector.geometry();
+    for copy in 0..geometry.num_fats {
+        device.write_sector(geometry.fat_copy_start(copy) as u64, &fat_sector)?;
+    }
+    
+    // écrire les entrées dans le cluster racine (secteur 2032)
+    let mut cluster_data = [0u8; 4096]; // 8 secteurs
+    for (i, entry) in [file1, file2, dir1].iter().enumerate() {
+        entry.write_to(&mut cluster_data[i * 32..])?;
+    }
+    device.write_sectors(geometry.data_start as u64, &cluster_data)?;
+    
+    std::fs::write(path, device.into_vec())?;
+    
+    println!("✓ Boot sector écrit");
+    println!("✓ Fichiers créés:");
+    println!("  - README.TXT (1024 octets)");
+    println!("  - DATA.BIN (2048 octets)");
+    println!("  - DOCS/ (répertoire)\n");
+    
+    Ok(())
+}
\ No newline at end of file
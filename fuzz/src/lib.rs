@@ -0,0 +1,163 @@
+//! cibles de fuzzing du parser FAT32
+//! 
+//! chaque cible place les octets fournis dans un petit volume en mémoire
+//! (boot sector, FAT ou répertoire racine) puis parcourt le volume avec
+//! les opérations de lecture. seules les paniques comptent : les erreurs
+//! retournées par le parser sont attendues sur une image corrompue.
+
+use fat32_parser::block_device::BlockDevice;
+use fat32_parser::devices::ram::RamBlockDevice;
+use fat32_parser::operations::directory::Visit;
+use fat32_parser::operations::parser::Fat32Parser;
+use fat32_parser::structures::boot_sector::BootSector;
+use fat32_parser::utils::validator::{self, MountOptions};
+
+/// taille du volume de test en secteurs
+const VOLUME_SECTORS: u32 = 1024;
+/// secteurs réservés avant la FAT
+const RESERVED_SECTORS: u16 = 32;
+/// taille d'une FAT en secteurs
+const FAT_SECTORS: u32 = 8;
+/// taille maximale lue par fichier
+const MAX_READ: usize = 64 * 1024;
+
+/// boot sector d'un volume vide de `VOLUME_SECTORS` secteurs
+fn boot_sector_template() -> BootSector {
+    BootSector {
+        jmp_boot: [0xEB, 0x58, 0x90],
+        oem_name: *b"MSWIN4.1",
+        bytes_per_sector: 512,
+        sectors_per_cluster: 1,
+        reserved_sector_count: RESERVED_SECTORS,
+        num_fats: 2,
+        root_entry_count: 0,
+        total_sectors_16: 0,
+        media_type: 0xF8,
+        fat_size_16: 0,
+        sectors_per_track: 63,
+        num_heads: 255,
+        hidden_sectors: 0,
+        total_sectors_32: VOLUME_SECTORS,
+        fat_size_32: FAT_SECTORS,
+        ext_flags: 0,
+        fs_version: 0,
+        root_cluster: 2,
+        fs_info_sector: 1,
+        backup_boot_sector: 6,
+        reserved: [0; 12],
+        drive_number: 0x80,
+        reserved1: 0,
+        boot_signature: 0x29,
+        volume_id: 0,
+        volume_label: *b"FUZZ       ",
+        fs_type: *b"FAT32   ",
+        boot_code: [0; 420],
+        signature: 0xAA55,
+    }
+}
+
+/// image d'un volume vide : racine au cluster 2, terminée dans la FAT
+fn empty_volume() -> Vec<u8> {
+    let mut image = vec![0u8; VOLUME_SECTORS as usize * 512];
+    image[..512].copy_from_slice(&boot_sector_template().to_bytes());
+    
+    let fat = RESERVED_SECTORS as usize * 512;
+    image[fat..fat + 12].copy_from_slice(&[0xF8, 0xFF, 0xFF, 0x0F, 0xFF, 0xFF, 0xFF, 0x0F, 0xF8, 0xFF, 0xFF, 0x0F]);
+    image
+}
+
+/// dispositif en mémoire de taille fixe
+fn device(image: Vec<u8>) -> RamBlockDevice {
+    RamBlockDevice::from_vec(image).with_max_sectors(VOLUME_SECTORS as u64)
+}
+
+/// parcourt le volume avec les opérations de lecture
+fn explore<D: BlockDevice>(parser: &Fat32Parser<D>) {
+    let root = parser.boot_sector().root_cluster;
+    let mut buffer = vec![0u8; MAX_READ];
+    
+    let _ = parser.read_root_dir();
+    let _ = parser.list_root_files();
+    let _ = parser.fat_usage();
+    let _ = parser.list_dir(root);
+    let _ = parser.walk_tree(&mut |entry, _, _| {
+        let _ = parser.chain_length(entry.first_cluster());
+        let _ = parser.read_file(entry.first_cluster(), &mut buffer);
+        Ok(Visit::Continue)
+    });
+    let _ = parser.list_deleted(root, |deleted| {
+        let _ = parser.recoverability(&deleted.entry);
+        Ok(Visit::Continue)
+    });
+    let _ = parser.for_each_file_slack(|_| Ok(Visit::Continue));
+    let _ = parser.for_each_unused_dir_slot(|_| Ok(Visit::Continue));
+    let _ = parser.for_each_truncated(|_| Ok(Visit::Continue));
+}
+
+/// boot sector arbitraire : décodage, validation et montage tolérant
+pub fn boot_sector(data: &[u8]) {
+    let mut sector = [0u8; 512];
+    let len = data.len().min(512);
+    sector[..len].copy_from_slice(&data[..len]);
+    
+    let boot_sector = BootSector::from_bytes(&sector);
+    assert_eq!(boot_sector.to_bytes(), sector);
+    let _ = validator::validate(&boot_sector, Some(VOLUME_SECTORS as u64));
+    for cluster in [0, 1, 2, boot_sector.root_cluster, boot_sector.max_cluster(), u32::MAX] {
+        let _ = boot_sector.checked_cluster_to_sector(cluster);
+        let _ = boot_sector.cluster_to_sector(cluster);
+    }
+    
+    let mut image = empty_volume();
+    image[..512].copy_from_slice(&sector);
+    if let Ok(parser) = Fat32Parser::mount_at(device(image), 0, MountOptions::lenient()) {
+        explore(&parser);
+    }
+}
+
+/// répertoire racine arbitraire, sur les premiers clusters de données
+pub fn directory(data: &[u8]) {
+    let mut image = empty_volume();
+    let boot_sector = boot_sector_template();
+    let start = boot_sector.data_start_sector() as usize * 512;
+    let len = data.len().min(image.len() - start);
+    image[start..start + len].copy_from_slice(&data[..len]);
+    
+    // les clusters couverts forment la chaîne de la racine
+    let clusters = len.div_ceil(512).max(1) as u32;
+    let fat = RESERVED_SECTORS as usize * 512;
+    for cluster in 2..2 + clusters {
+        let next = if cluster == 1 + clusters { 0x0FFF_FFFF } else { cluster + 1 };
+        let offset = fat + cluster as usize * 4;
+        image[offset..offset + 4].copy_from_slice(&next.to_le_bytes());
+    }
+    
+    if let Ok(parser) = Fat32Parser::new(device(image)) {
+        explore(&parser);
+    }
+}
+
+/// FAT arbitraire : chaînes qui bouclent, sortent du volume ou
+/// pointent vers des clusters réservés
+pub fn chain(data: &[u8]) {
+    let mut image = empty_volume();
+    let fat = RESERVED_SECTORS as usize * 512;
+    let len = data.len().min(FAT_SECTORS as usize * 512);
+    image[fat..fat + len].copy_from_slice(&data[..len]);
+    
+    let Ok(parser) = Fat32Parser::new(device(image)) else {
+        return;
+    };
+    let mut buffer = vec![0u8; MAX_READ];
+    for head in data.chunks_exact(4).take(8) {
+        let head = u32::from_le_bytes([head[0], head[1], head[2], head[3]]);
+        let _ = parser.chain_length(head);
+        let _ = parser.follow_cluster_chain(head);
+        let _ = parser.chain_cluster_at(head, 3);
+        let _ = parser.read_file(head, &mut buffer);
+        let _ = parser.list_dir(head);
+    }
+    let _ = parser.count_free_clusters();
+    let _ = parser.find_free_cluster();
+    explore(&parser);
+}
@@ -0,0 +1,429 @@
+//! cache de secteurs au-dessus d'un BlockDevice
+//! 
+//! `CachedDevice` garde les derniers secteurs lus dans des emplacements
+//! fournis par l'appelant (aucune allocation). le remplacement est LRU,
+//! mais les secteurs de FAT ne sont évincés que si tous les emplacements
+//! occupés sont des secteurs de FAT : ce sont eux qui sont relus le plus
+//! souvent lors du suivi des chaînes.
+//! 
+//! en mode `WriteBack`, les écritures restent en cache et sont regroupées
+//! jusqu'à `flush`, l'éviction du secteur ou la destruction du cache. les
+//! secteurs sont alors écrits dans l'ordre de `SectorKind` : données,
+//! puis FAT, puis répertoires, pour qu'une coupure ne laisse jamais une
+//! entrée pointer vers des clusters non écrits. la nature d'un secteur
+//! est celle passée à `write_sector_kind` ; `write_sector` écrit des
+//! données.
+//! 
+//! # Exemples
+//! 
+//! ```no_run
+//! use fat32_parser::devices::cache::{CacheMode, CacheSlot, CachedDevice};
+//! # use fat32_parser::block_device::BlockDeviceMut;
+//! # use fat32_parser::error::Fat32Error;
+//! # use fat32_parser::parser::Fat32Parser;
+//! # fn exemple<D: BlockDeviceMut>(mon_device: D) -> Result<(), Fat32Error<D::Error>> {
+//! 
+//! static mut SLOTS: [CacheSlot; 16] = [CacheSlot::EMPTY; 16];
+//! 
+//! let slots = unsafe { &mut *core::ptr::addr_of_mut!(SLOTS) };
+//! let device = CachedDevice::new(mon_device, slots)
+//!     .with_fat_region(32, 2 * 1000)
+//!     .with_mode(CacheMode::WriteBack);
+//! let mut parser = Fat32Parser::new(device)?;
+//! // ...
+//! parser.flush()?;
+//! # Ok(())
+//! # }
+//! ```
+
+use core::cell::{Ref, RefCell};
+use crate::traits::block_device::{BlockDevice, BlockDeviceMut, SectorKind};
+use crate::utils::error::Fat32Error;
+
+/// ordre d'écriture des secteurs différés
+const WRITE_ORDER: [SectorKind; 3] = [SectorKind::Data, SectorKind::Fat, SectorKind::Directory];
+
+/// politique d'écriture du cache
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum CacheMode {
+    /// chaque écriture est transmise immédiatement au dispositif
+    #[default]
+    WriteThrough,
+    /// les écritures sont différées jusqu'à `flush` ou l'éviction
+    WriteBack,
+}
+
+/// emplacement du cache, un secteur
+#[derive(Clone, Copy)]
+pub struct CacheSlot {
+    data: [u8; 512],
+    sector: u64,
+    valid: bool,
+    dirty: bool,
+    kind: SectorKind,
+    last_use: u32,
+}
+
+impl CacheSlot {
+    /// emplacement vide, pour initialiser un tableau statique
+    pub const EMPTY: CacheSlot = CacheSlot {
+        data: [0; 512],
+        sector: 0,
+        valid: false,
+        dirty: false,
+        kind: SectorKind::Data,
+        last_use: 0,
+    };
+}
+
+impl Default for CacheSlot {
+    fn default() -> Self {
+        Self::EMPTY
+    }
+}
+
+/// compteurs du cache
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CacheStats {
+    /// lectures servies par le cache
+    pub hits: u32,
+    /// lectures transmises au dispositif
+    pub misses: u32,
+    /// secteurs retirés du cache pour faire de la place
+    pub evictions: u32,
+    /// secteurs différés écrits sur le dispositif
+    pub write_backs: u32,
+}
+
+/// écriture d'un secteur sur le dispositif sous-jacent
+type WriteFn<D> = fn(&mut D, u64, &[u8], SectorKind) -> Result<(), Fat32Error<<D as BlockDevice>::Error>>;
+
+/// écritures du dispositif sous-jacent
+/// 
+/// enregistrées à la première écriture, seule opération qui exige
+/// `BlockDeviceMut`. un secteur différé n'existe qu'après elle : une
+/// lecture qui l'évince ou la destruction du cache peuvent alors l'écrire,
+/// quel que soit le dispositif.
+struct Writer<D: BlockDevice> {
+    write: WriteFn<D>,
+    flush: fn(&mut D) -> Result<(), Fat32Error<D::Error>>,
+}
+
+impl<D: BlockDevice> Clone for Writer<D> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<D: BlockDevice> Copy for Writer<D> {}
+
+struct CacheState<'a> {
+    slots: &'a mut [CacheSlot],
+    clock: u32,
+    stats: CacheStats,
+}
+
+/// dispositif avec cache de secteurs
+/// 
+/// à sa destruction, les secteurs différés sont écrits ; les erreurs sont
+/// alors ignorées, d'où l'intérêt d'appeler `flush` avant.
+pub struct CachedDevice<'a, D: BlockDevice> {
+    /// dispositif sous-jacent, retiré seulement par `into_inner`
+    device: Option<RefCell<D>>,
+    state: RefCell<CacheState<'a>>,
+    writer: Option<Writer<D>>,
+    mode: CacheMode,
+    fat_start: u64,
+    fat_end: u64,
+}
+
+impl<'a, D: BlockDevice> CachedDevice<'a, D> {
+    /// crée un cache utilisant `slots` comme stockage
+    pub fn new(device: D, slots: &'a mut [CacheSlot]) -> Self {
+        slots.iter_mut().for_each(|slot| *slot = CacheSlot::EMPTY);
+        Self {
+            device: Some(RefCell::new(device)),
+            state: RefCell::new(CacheState {
+                slots,
+                clock: 0,
+                stats: CacheStats::default(),
+            }),
+            writer: None,
+            mode: CacheMode::WriteThrough,
+            fat_start: 0,
+            fat_end: 0,
+        }
+    }
+    
+    /// indique les secteurs occupés par les FAT, qui restent en cache
+    /// plus longtemps que les autres secteurs lus
+    /// 
+    /// n'influe que sur l'éviction : l'ordre d'écriture ne dépend que de
+    /// la nature donnée à chaque écriture.
+    pub fn with_fat_region(mut self, first_sector: u64, sector_count: u64) -> Self {
+        self.fat_start = first_sector;
+        self.fat_end = first_sector.saturating_add(sector_count);
+        self
+    }
+    
+    /// choisit la politique d'écriture
+    pub fn with_mode(mut self, mode: CacheMode) -> Self {
+        self.mode = mode;
+        self
+    }
+    
+    /// retourne la politique d'écriture
+    pub fn mode(&self) -> CacheMode {
+        self.mode
+    }
+    
+    /// retourne les compteurs
+    pub fn stats(&self) -> CacheStats {
+        self.state.borrow().stats
+    }
+    
+    /// remet les compteurs à zéro
+    pub fn reset_stats(&self) {
+        self.state.borrow_mut().stats = CacheStats::default();
+    }
+    
+    /// nombre de secteurs en attente d'écriture
+    pub fn dirty_count(&self) -> usize {
+        self.state.borrow().slots.iter().filter(|slot| slot.valid && slot.dirty).count()
+    }
+    
+    /// vide le cache sans toucher au dispositif
+    /// 
+    /// les écritures différées sont perdues.
+    pub fn invalidate(&self) {
+        self.state.borrow_mut().slots.iter_mut().for_each(|slot| *slot = CacheSlot::EMPTY);
+    }
+    
+    /// retourne le dispositif sous-jacent
+    pub fn inner(&self) -> Ref<'_, D> {
+        self.device().borrow()
+    }
+    
+    /// écrit les secteurs différés et rend le dispositif sous-jacent
+    pub fn into_inner(mut self) -> Result<D, Fat32Error<D::Error>> {
+        self.write_back_all()?;
+        // `drop` ne trouve plus de dispositif et n'écrit rien
+        let device = self.device.take().expect("dispositif présent jusqu'à into_inner");
+        Ok(device.into_inner())
+    }
+    
+    fn device(&self) -> &RefCell<D> {
+        self.device.as_ref().expect("dispositif présent jusqu'à into_inner")
+    }
+    
+    /// écrit les secteurs différés (données, FAT puis répertoires)
+    fn write_back_all(&mut self) -> Result<(), Fat32Error<D::Error>> {
+        let (Some(writer), Some(device)) = (self.writer, self.device.as_mut()) else {
+            return Ok(());
+        };
+        let device = device.get_mut();
+        let state = self.state.get_mut();
+        
+        for kind in WRITE_ORDER {
+            state.write_back_kind(device, writer.write, kind)?;
+        }
+        
+        (writer.flush)(device)
+    }
+    
+    /// nature d'un secteur lu, pour le choix des secteurs à évincer
+    fn classify(&self, sector: u64) -> SectorKind {
+        if sector >= self.fat_start && sector < self.fat_end {
+            SectorKind::Fat
+        } else {
+            SectorKind::Data
+        }
+    }
+}
+
+impl CacheState<'_> {
+    fn tick(&mut self) -> u32 {
+        self.clock = self.clock.wrapping_add(1);
+        self.clock
+    }
+    
+    fn find(&self, sector: u64) -> Option<usize> {
+        self.slots.iter().position(|slot| slot.valid && slot.sector == sector)
+    }
+    
+    /// choisit l'emplacement à remplacer
+    /// 
+    /// un emplacement libre d'abord, puis le moins récemment utilisé des
+    /// secteurs hors FAT, et seulement en dernier recours un secteur de FAT.
+    fn victim(&self) -> Option<usize> {
+        if let Some(free) = self.slots.iter().position(|slot| !slot.valid) {
+            return Some(free);
+        }
+        
+        let age = |slot: &CacheSlot| self.clock.wrapping_sub(slot.last_use);
+        let oldest = |fat: bool| {
+            self.slots.iter()
+                .enumerate()
+                .filter(|(_, slot)| (slot.kind == SectorKind::Fat) == fat)
+                .max_by_key(|(_, slot)| age(slot))
+                .map(|(i, _)| i)
+        };
+        
+        oldest(false).or_else(|| oldest(true))
+    }
+    
+    /// écrit un emplacement différé sur le dispositif
+    fn write_back<D: BlockDevice>(&mut self, device: &mut D, write: WriteFn<D>, i: usize) -> Result<(), Fat32Error<D::Error>> {
+        let slot = &mut self.slots[i];
+        write(device, slot.sector, &slot.data, slot.kind)?;
+        slot.dirty = false;
+        self.stats.write_backs += 1;
+        Ok(())
+    }
+    
+    /// écrit tous les emplacements différés d'une nature
+    fn write_back_kind<D: BlockDevice>(
+        &mut self,
+        device: &mut D,
+        write: WriteFn<D>,
+        kind: SectorKind,
+    ) -> Result<(), Fat32Error<D::Error>> {
+        for i in 0..self.slots.len() {
+            let slot = &self.slots[i];
+            if slot.valid && slot.dirty && slot.kind == kind {
+                self.write_back(device, write, i)?;
+            }
+        }
+        Ok(())
+    }
+    
+    /// libère un emplacement et retourne son indice
+    /// 
+    /// un secteur différé n'est écrit qu'après tous ceux des natures qui
+    /// le précèdent, pour respecter l'ordre même lors d'une éviction.
+    fn make_room<D: BlockDevice>(
+        &mut self,
+        device: &mut D,
+        writer: Option<Writer<D>>,
+    ) -> Result<Option<usize>, Fat32Error<D::Error>> {
+        let Some(i) = self.victim() else {
+            return Ok(None);
+        };
+        
+        let slot = self.slots[i];
+        if slot.valid && slot.dirty {
+            // un secteur différé implique une écriture, donc un `Writer`
+            let write = writer.ok_or(Fat32Error::ReadOnly)?.write;
+            for kind in WRITE_ORDER.iter().copied().filter(|&kind| kind < slot.kind) {
+                self.write_back_kind(device, write, kind)?;
+            }
+            self.write_back(device, write, i)?;
+        }
+        if slot.valid {
+            self.stats.evictions += 1;
+        }
+        
+        self.slots[i].valid = false;
+        Ok(Some(i))
+    }
+    
+    fn fill(&mut self, i: usize, sector: u64, data: &[u8], kind: SectorKind, dirty: bool) {
+        let now = self.clock;
+        let slot = &mut self.slots[i];
+        slot.data.copy_from_slice(data);
+        slot.sector = sector;
+        slot.valid = true;
+        slot.dirty = dirty;
+        slot.kind = kind;
+        slot.last_use = now;
+    }
+}
+
+impl<D: BlockDevice> BlockDevice for CachedDevice<'_, D> {
+    type Error = D::Error;
+    
+    fn read_sector(&self, sector: u64, buffer: &mut [u8]) -> Result<(), Fat32Error<D::Error>> {
+        let mut state = self.state.borrow_mut();
+        let now = state.tick();
+        
+        if let Some(i) = state.find(sector) {
+            state.stats.hits += 1;
+            let slot = &mut state.slots[i];
+            slot.last_use = now;
+            buffer.copy_from_slice(&slot.data);
+            return Ok(());
+        }
+        
+        state.stats.misses += 1;
+        self.device().borrow().read_sector(sector, buffer)?;
+        
+        if let Some(i) = state.make_room(&mut *self.device().borrow_mut(), self.writer)? {
+            state.fill(i, sector, buffer, self.classify(sector), false);
+        }
+        
+        Ok(())
+    }
+    
+    fn sector_size(&self) -> u32 {
+        self.device().borrow().sector_size()
+    }
+    
+    fn sector_count(&self) -> Option<u64> {
+        self.device().borrow().sector_count()
+    }
+}
+
+impl<D: BlockDeviceMut> CachedDevice<'_, D> {
+    /// enregistre les écritures du dispositif sous-jacent
+    fn writer(&mut self) -> Writer<D> {
+        *self.writer.get_or_insert(Writer {
+            write: D::write_sector_kind,
+            flush: D::flush,
+        })
+    }
+}
+
+impl<D: BlockDeviceMut> BlockDeviceMut for CachedDevice<'_, D> {
+    fn write_sector(&mut self, sector: u64, buffer: &[u8]) -> Result<(), Fat32Error<D::Error>> {
+        self.write_sector_kind(sector, buffer, SectorKind::Data)
+    }
+    
+    fn write_sector_kind(&mut self, sector: u64, buffer: &[u8], kind: SectorKind) -> Result<(), Fat32Error<D::Error>> {
+        let writer = self.writer();
+        let device = self.device.as_mut().expect("dispositif présent jusqu'à into_inner").get_mut();
+        let state = self.state.get_mut();
+        state.tick();
+        
+        if self.mode == CacheMode::WriteThrough {
+            device.write_sector_kind(sector, buffer, kind)?;
+            if let Some(i) = state.find(sector) {
+                state.fill(i, sector, buffer, kind, false);
+            }
+            return Ok(());
+        }
+        
+        let slot = match state.find(sector) {
+            Some(i) => Some(i),
+            None => state.make_room(device, Some(writer))?,
+        };
+        match slot {
+            Some(i) => state.fill(i, sector, buffer, kind, true),
+            // aucun emplacement : écriture directe
+            None => device.write_sector_kind(sector, buffer, kind)?,
+        }
+        
+        Ok(())
+    }
+    
+    /// écrit les secteurs différés (données, FAT puis répertoires)
+    fn flush(&mut self) -> Result<(), Fat32Error<D::Error>> {
+        self.writer();
+        self.write_back_all()
+    }
+}
+
+impl<D: BlockDevice> Drop for CachedDevice<'_, D> {
+    fn drop(&mut self) {
+        let _ = self.write_back_all();
+    }
+}
@@ -0,0 +1,229 @@
+//! dispositifs sur fichier (feature `std`)
+//! 
+//! `FileBlockDevice` lit et écrit une image disque par des entrées/sorties
+//! positionnelles, sans déplacer de curseur partagé ; une fenêtre (premier
+//! secteur et longueur) restreint l'accès à une partition du fichier.
+//! `FileDevice` en est la version en lecture seule : il n'implémente que
+//! `BlockDevice`, et un parser construit dessus n'expose aucune opération
+//! d'écriture. les erreurs du système sont transmises avec leur
+//! `ErrorKind` dans `Fat32Error::Device`.
+//! 
+//! les entrées/sorties positionnelles passent par `FileExt` : `read_at` et
+//! `write_at` sous Unix, `seek_read` et `seek_write` sous Windows.
+
+use std::fs::{File, OpenOptions};
+use std::io::ErrorKind;
+use std::path::Path;
+use crate::traits::block_device::{BlockDevice, BlockDeviceMut};
+use crate::utils::error::Fat32Error;
+
+/// lit `buffer.len()` octets à la position `offset` du fichier
+#[cfg(unix)]
+pub(crate) fn read_exact_at(file: &File, buffer: &mut [u8], offset: u64) -> std::io::Result<()> {
+    std::os::unix::fs::FileExt::read_exact_at(file, buffer, offset)
+}
+
+/// lit `buffer.len()` octets à la position `offset` du fichier
+/// 
+/// `seek_read` peut lire moins que demandé : on reprend jusqu'à remplir
+/// le tampon, et une lecture vide signale la fin du fichier.
+#[cfg(windows)]
+pub(crate) fn read_exact_at(file: &File, mut buffer: &mut [u8], mut offset: u64) -> std::io::Result<()> {
+    use std::os::windows::fs::FileExt;
+    
+    while !buffer.is_empty() {
+        match file.seek_read(buffer, offset) {
+            Ok(0) => return Err(ErrorKind::UnexpectedEof.into()),
+            Ok(read) => {
+                buffer = &mut buffer[read..];
+                offset += read as u64;
+            }
+            Err(e) if e.kind() == ErrorKind::Interrupted => {}
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(())
+}
+
+/// écrit tout `buffer` à la position `offset` du fichier
+#[cfg(unix)]
+pub(crate) fn write_all_at(file: &File, buffer: &[u8], offset: u64) -> std::io::Result<()> {
+    std::os::unix::fs::FileExt::write_all_at(file, buffer, offset)
+}
+
+/// écrit tout `buffer` à la position `offset` du fichier
+#[cfg(windows)]
+pub(crate) fn write_all_at(file: &File, mut buffer: &[u8], mut offset: u64) -> std::io::Result<()> {
+    use std::os::windows::fs::FileExt;
+    
+    while !buffer.is_empty() {
+        match file.seek_write(buffer, offset) {
+            Ok(0) => return Err(ErrorKind::WriteZero.into()),
+            Ok(written) => {
+                buffer = &buffer[written..];
+                offset += written as u64;
+            }
+            Err(e) if e.kind() == ErrorKind::Interrupted => {}
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(())
+}
+
+/// image disque accessible en lecture et en écriture
+pub struct FileBlockDevice {
+    file: File,
+    /// premier secteur de la fenêtre dans le fichier
+    offset: u64,
+    /// longueur de la fenêtre en secteurs, jusqu'à la fin du fichier si
+    /// `None`
+    length: Option<u64>,
+}
+
+impl FileBlockDevice {
+    /// ouvre l'image située à `path` en lecture et en écriture
+    pub fn open<P: AsRef<Path>>(path: P) -> std::io::Result<Self> {
+        Ok(Self::from_file(OpenOptions::new().read(true).write(true).open(path)?))
+    }
+    
+    /// utilise un fichier déjà ouvert
+    pub fn from_file(file: File) -> Self {
+        Self {
+            file,
+            offset: 0,
+            length: None,
+        }
+    }
+    
+    /// fait commencer le dispositif au secteur `offset` du fichier
+    pub fn with_offset(mut self, offset: u64) -> Self {
+        self.offset = offset;
+        self
+    }
+    
+    /// limite le dispositif à `length` secteurs
+    pub fn with_length(mut self, length: u64) -> Self {
+        self.length = Some(length);
+        self
+    }
+    
+    /// rend le fichier
+    pub fn into_file(self) -> File {
+        self.file
+    }
+    
+    /// position dans le fichier de `count` secteurs à partir de `sector`
+    fn position(&self, sector: u64, count: u64) -> Result<u64, Fat32Error<ErrorKind>> {
+        let end = sector.checked_add(count).ok_or(Fat32Error::InvalidSector { sector })?;
+        if self.length.is_some_and(|length| end > length) {
+            return Err(Fat32Error::InvalidSector { sector });
+        }
+        self.offset
+            .checked_add(sector)
+            .and_then(|sector| sector.checked_mul(512))
+            .ok_or(Fat32Error::InvalidSector { sector })
+    }
+    
+    fn read_at(&self, sector: u64, buffer: &mut [u8]) -> Result<(), Fat32Error<ErrorKind>> {
+        let position = self.position(sector, (buffer.len() / 512) as u64)?;
+        read_exact_at(&self.file, buffer, position).map_err(|e| match e.kind() {
+            ErrorKind::UnexpectedEof => Fat32Error::InvalidSector { sector },
+            kind => Fat32Error::Device { sector, error: kind },
+        })
+    }
+    
+    fn write_at(&self, sector: u64, buffer: &[u8]) -> Result<(), Fat32Error<ErrorKind>> {
+        let position = self.position(sector, (buffer.len() / 512) as u64)?;
+        write_all_at(&self.file, buffer, position)
+            .map_err(|e| Fat32Error::Device { sector, error: e.kind() })
+    }
+}
+
+impl BlockDevice for FileBlockDevice {
+    type Error = ErrorKind;
+    
+    fn read_sector(&self, sector: u64, buffer: &mut [u8]) -> Result<(), Fat32Error<ErrorKind>> {
+        self.read_at(sector, buffer)
+    }
+    
+    // un seul appel système pour plusieurs secteurs
+    fn read_sectors(&self, start: u64, buffer: &mut [u8]) -> Result<(), Fat32Error<ErrorKind>> {
+        if !buffer.len().is_multiple_of(512) {
+            return Err(Fat32Error::BufferTooSmall);
+        }
+        self.read_at(start, buffer)
+    }
+    
+    // un secteur incomplet en fin de fichier n'est pas lisible
+    fn sector_count(&self) -> Option<u64> {
+        let available = (self.file.metadata().ok()?.len() / 512).saturating_sub(self.offset);
+        Some(self.length.map_or(available, |length| length.min(available)))
+    }
+}
+
+impl BlockDeviceMut for FileBlockDevice {
+    fn write_sector(&mut self, sector: u64, buffer: &[u8]) -> Result<(), Fat32Error<ErrorKind>> {
+        self.write_at(sector, buffer)
+    }
+    
+    fn write_sectors(&mut self, start: u64, buffer: &[u8]) -> Result<(), Fat32Error<ErrorKind>> {
+        if !buffer.len().is_multiple_of(512) {
+            return Err(Fat32Error::BufferTooSmall);
+        }
+        self.write_at(start, buffer)
+    }
+    
+    // le secteur signalé en cas d'échec est le début de la fenêtre
+    fn flush(&mut self) -> Result<(), Fat32Error<ErrorKind>> {
+        self.file.sync_data().map_err(|e| Fat32Error::Device { sector: 0, error: e.kind() })
+    }
+}
+
+/// image disque ouverte en lecture seule
+pub struct FileDevice {
+    inner: FileBlockDevice,
+}
+
+impl FileDevice {
+    /// ouvre l'image située à `path`
+    pub fn new<P: AsRef<Path>>(path: P) -> std::io::Result<Self> {
+        Ok(Self::from_file(File::open(path)?))
+    }
+    
+    /// utilise un fichier déjà ouvert
+    pub fn from_file(file: File) -> Self {
+        Self {
+            inner: FileBlockDevice::from_file(file),
+        }
+    }
+    
+    /// fait commencer le dispositif au secteur `offset` du fichier
+    pub fn with_offset(self, offset: u64) -> Self {
+        Self {
+            inner: self.inner.with_offset(offset),
+        }
+    }
+    
+    /// limite le dispositif à `length` secteurs
+    pub fn with_length(self, length: u64) -> Self {
+        Self {
+            inner: self.inner.with_length(length),
+        }
+    }
+}
+
+impl BlockDevice for FileDevice {
+    type Error = ErrorKind;
+    
+    fn read_sector(&self, sector: u64, buffer: &mut [u8]) -> Result<(), Fat32Error<ErrorKind>> {
+        self.inner.read_sector(sector, buffer)
+    }
+    
+    fn read_sectors(&self, start: u64, buffer: &mut [u8]) -> Result<(), Fat32Error<ErrorKind>> {
+        self.inner.read_sectors(start, buffer)
+    }
+    
+    fn sector_count(&self) -> Option<u64> {
+        self.inner.sector_count()
+    }
+}
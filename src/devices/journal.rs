@@ -0,0 +1,361 @@
+//! journal d'intentions pour les mises à jour de métadonnées
+//! 
+//! `JournaledDevice` utilise les secteurs réservés inutilisés (après la
+//! copie de secours du boot sector) comme journal. pendant une
+//! transaction, les écritures de FAT et de répertoire sont placées dans le
+//! journal au lieu de leur secteur cible ; les lectures voient les
+//! versions journalisées. la validation écrit l'en-tête du journal en une
+//! seule écriture de secteur, puis recopie chaque secteur à sa place.
+//! 
+//! les secteurs cibles sont enregistrés relativement au début du volume,
+//! ce qui permet de journaliser une partition placée n'importe où sur un
+//! grand disque.
+//! 
+//! à l'ouverture, un journal validé est rejoué et un journal incomplet est
+//! abandonné : une coupure laisse donc les métadonnées soit avant, soit
+//! après la transaction. les écritures de données ne sont pas
+//! journalisées et partent immédiatement, avant toute métadonnée.
+//! 
+//! # Disposition
+//! 
+//! ```text
+//! secteur start      en-tête : "FAT32JNL", état, nombre, checksum, cibles
+//! secteur start + i  nouveau contenu du i-ème secteur cible
+//! ```
+
+use crate::operations::parser::Fat32Parser;
+use crate::structures::boot_sector::BootSector;
+use crate::traits::block_device::{BlockDevice, BlockDeviceMut, SectorKind};
+use crate::utils::error::Fat32Error;
+use crate::utils::helpers::{fnv1a, FNV1A_INIT};
+
+/// signature de l'en-tête du journal
+const JOURNAL_MAGIC: &[u8; 8] = b"FAT32JNL";
+
+/// journal sans transaction validée
+const STATE_EMPTY: u32 = 0;
+
+/// transaction validée, à recopier
+const STATE_COMMITTED: u32 = 1;
+
+/// taille de l'en-tête avant la liste des cibles
+const HEADER_SIZE: usize = 20;
+
+/// nombre maximal de secteurs dans une transaction
+pub const JOURNAL_MAX_SECTORS: usize = (512 - HEADER_SIZE) / 4;
+
+/// résultat de l'examen du journal à l'ouverture
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Recovery {
+    /// aucune transaction en attente
+    Clean,
+    /// une transaction validée a été rejouée
+    Replayed { sectors: u32 },
+    /// une transaction incomplète ou corrompue a été abandonnée
+    Discarded,
+}
+
+/// dispositif avec journal des métadonnées
+pub struct JournaledDevice<D: BlockDeviceMut> {
+    device: D,
+    base: u64,
+    start: u32,
+    capacity: usize,
+    targets: [u32; JOURNAL_MAX_SECTORS],
+    count: usize,
+    active: bool,
+}
+
+impl<D: BlockDeviceMut> JournaledDevice<D> {
+    /// ouvre le journal d'un volume et rejoue ou abandonne la transaction
+    /// en attente
+    /// 
+    /// retourne `BufferTooSmall` si les secteurs réservés libres ne
+    /// peuvent pas contenir un en-tête et au moins un secteur.
+    pub fn open(device: D) -> Result<(Self, Recovery), Fat32Error<D::Error>> {
+        Self::open_at(device, 0)
+    }
+    
+    /// ouvre le journal du volume commençant au secteur `partition_start`
+    pub fn open_at(mut device: D, partition_start: u64) -> Result<(Self, Recovery), Fat32Error<D::Error>> {
+        let mut buffer = [0u8; 512];
+        device.read_sector(partition_start, &mut buffer)?;
+        let boot_sector = BootSector::parse(&buffer).map_err(Fat32Error::widen)?;
+        if !boot_sector.is_valid() {
+            return Err(Fat32Error::InvalidSignature { sector: partition_start });
+        }
+        
+        // boot sector, FSInfo et secteur suivant, ainsi que leur copie
+        let backup = boot_sector.backup_boot_sector as u32;
+        let after_backup = if backup != 0 { backup + 3 } else { 0 };
+        let start = after_backup.max(boot_sector.fs_info_sector as u32 + 1).max(3);
+        let end = boot_sector.reserved_sector_count as u32;
+        if end < start + 2 {
+            return Err(Fat32Error::BufferTooSmall);
+        }
+        
+        let capacity = core::cmp::min(JOURNAL_MAX_SECTORS, (end - start - 1) as usize);
+        let recovery = replay(&mut device, partition_start, start, capacity, boot_sector.total_sectors())?;
+        
+        Ok((
+            Self {
+                device,
+                base: partition_start,
+                start,
+                capacity,
+                targets: [0; JOURNAL_MAX_SECTORS],
+                count: 0,
+                active: false,
+            },
+            recovery,
+        ))
+    }
+    
+    /// nombre maximal de secteurs de métadonnées par transaction
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+    
+    /// vérifie si une transaction est en cours
+    pub fn in_transaction(&self) -> bool {
+        self.active
+    }
+    
+    /// démarre une transaction
+    pub fn begin(&mut self) -> Result<(), Fat32Error<D::Error>> {
+        if self.active {
+            return Err(Fat32Error::AlreadyExists);
+        }
+        self.active = true;
+        self.count = 0;
+        Ok(())
+    }
+    
+    /// valide la transaction en cours et l'applique
+    pub fn commit(&mut self) -> Result<(), Fat32Error<D::Error>> {
+        if !self.active {
+            return Err(Fat32Error::NotFound);
+        }
+        self.active = false;
+        if self.count == 0 {
+            return Ok(());
+        }
+        
+        // les secteurs du journal doivent être sur le support avant l'en-tête
+        self.device.flush()?;
+        
+        let mut header = [0u8; 512];
+        header[0..8].copy_from_slice(JOURNAL_MAGIC);
+        header[8..12].copy_from_slice(&STATE_COMMITTED.to_le_bytes());
+        header[12..16].copy_from_slice(&(self.count as u32).to_le_bytes());
+        header[16..20].copy_from_slice(&checksum(&self.device, self.journal_sector(0), &self.targets[..self.count])?.to_le_bytes());
+        for (i, target) in self.targets[..self.count].iter().enumerate() {
+            let offset = HEADER_SIZE + i * 4;
+            header[offset..offset + 4].copy_from_slice(&target.to_le_bytes());
+        }
+        
+        // point de validation
+        self.device.write_sector_kind(self.journal_sector(0), &header, SectorKind::Directory)?;
+        self.device.flush()?;
+        
+        // cibles écrites par ce dispositif : pas de borne sur le volume
+        replay(&mut self.device, self.base, self.start, self.capacity, u32::MAX)?;
+        self.count = 0;
+        Ok(())
+    }
+    
+    /// abandonne la transaction en cours
+    /// 
+    /// les secteurs cibles n'ont pas été modifiés ; l'état gardé en mémoire
+    /// par le parser (FSInfo, bitmap) peut en revanche être à recharger.
+    pub fn abort(&mut self) {
+        self.active = false;
+        self.count = 0;
+    }
+    
+    /// retourne le dispositif sous-jacent
+    pub fn inner(&self) -> &D {
+        &self.device
+    }
+    
+    /// rend le dispositif sous-jacent
+    /// 
+    /// une transaction en cours est perdue.
+    pub fn into_inner(self) -> D {
+        self.device
+    }
+    
+    /// secteur du dispositif occupé par l'emplacement `slot` du journal
+    /// 
+    /// l'emplacement 0 est l'en-tête.
+    fn journal_sector(&self, slot: usize) -> u64 {
+        self.base + self.start as u64 + slot as u64
+    }
+    
+    /// numéro de secteur relatif au volume, s'il en fait partie
+    fn relative(&self, sector: u64) -> Option<u32> {
+        sector.checked_sub(self.base).and_then(|relative| u32::try_from(relative).ok())
+    }
+    
+    /// secteur du journal contenant la version en attente d'un secteur
+    fn staged(&self, sector: u64) -> Option<u64> {
+        let relative = self.relative(sector)?;
+        self.targets[..self.count]
+            .iter()
+            .position(|&target| target == relative)
+            .map(|i| self.journal_sector(1 + i))
+    }
+}
+
+impl<D: BlockDeviceMut> BlockDevice for JournaledDevice<D> {
+    type Error = D::Error;
+    
+    fn read_sector(&self, sector: u64, buffer: &mut [u8]) -> Result<(), Fat32Error<D::Error>> {
+        let source = self.staged(sector).unwrap_or(sector);
+        self.device.read_sector(source, buffer)
+    }
+    
+    fn sector_size(&self) -> u32 {
+        self.device.sector_size()
+    }
+    
+    fn sector_count(&self) -> Option<u64> {
+        self.device.sector_count()
+    }
+}
+
+impl<D: BlockDeviceMut> BlockDeviceMut for JournaledDevice<D> {
+    fn write_sector(&mut self, sector: u64, buffer: &[u8]) -> Result<(), Fat32Error<D::Error>> {
+        self.write_sector_kind(sector, buffer, SectorKind::Data)
+    }
+    
+    /// journalise les écritures de FAT et de répertoire d'une transaction
+    /// 
+    /// retourne `BufferTooSmall` si la transaction dépasse la capacité du
+    /// journal ; elle peut alors être abandonnée.
+    fn write_sector_kind(&mut self, sector: u64, buffer: &[u8], kind: SectorKind) -> Result<(), Fat32Error<D::Error>> {
+        if sector >= self.journal_sector(0) && sector <= self.journal_sector(self.capacity) {
+            return Err(Fat32Error::InvalidSector { sector });
+        }
+        
+        if let Some(slot) = self.staged(sector) {
+            return self.device.write_sector_kind(slot, buffer, SectorKind::Data);
+        }
+        let relative = match self.relative(sector) {
+            Some(relative) if self.active && kind != SectorKind::Data => relative,
+            _ => return self.device.write_sector_kind(sector, buffer, kind),
+        };
+        
+        if self.count == self.capacity {
+            return Err(Fat32Error::BufferTooSmall);
+        }
+        self.device.write_sector_kind(self.journal_sector(1 + self.count), buffer, SectorKind::Data)?;
+        self.targets[self.count] = relative;
+        self.count += 1;
+        Ok(())
+    }
+    
+    fn flush(&mut self) -> Result<(), Fat32Error<D::Error>> {
+        self.device.flush()
+    }
+}
+
+/// FNV-1a des cibles et du contenu journalisé
+fn checksum<D: BlockDevice>(device: &D, header: u64, targets: &[u32]) -> Result<u32, Fat32Error<D::Error>> {
+    let mut hash = FNV1A_INIT;
+    let mut buffer = [0u8; 512];
+    for (i, target) in targets.iter().enumerate() {
+        hash = fnv1a(hash, &target.to_le_bytes());
+        device.read_sector(header + 1 + i as u64, &mut buffer)?;
+        hash = fnv1a(hash, &buffer);
+    }
+    
+    Ok(hash)
+}
+
+/// rejoue ou abandonne la transaction décrite par l'en-tête
+/// 
+/// l'en-tête vient du disque : une transaction plus grande que le journal
+/// ou visant un secteur hors du volume est abandonnée sans être rejouée.
+fn replay<D: BlockDeviceMut>(
+    device: &mut D,
+    base: u64,
+    start: u32,
+    capacity: usize,
+    total_sectors: u32,
+) -> Result<Recovery, Fat32Error<D::Error>> {
+    let header_sector = base + start as u64;
+    let mut header = [0u8; 512];
+    device.read_sector(header_sector, &mut header)?;
+    if &header[0..8] != JOURNAL_MAGIC {
+        return Ok(Recovery::Clean);
+    }
+    
+    let word = |offset: usize| u32::from_le_bytes([header[offset], header[offset + 1], header[offset + 2], header[offset + 3]]);
+    let state = word(8);
+    let count = word(12) as usize;
+    
+    let mut targets = [0u32; JOURNAL_MAX_SECTORS];
+    let valid = state == STATE_COMMITTED && count <= capacity && {
+        for (i, target) in targets[..count].iter_mut().enumerate() {
+            *target = word(HEADER_SIZE + i * 4);
+        }
+        targets[..count].iter().all(|&target| target < total_sectors && (target < start || target > start + capacity as u32))
+            && checksum(device, header_sector, &targets[..count])? == word(16)
+    };
+    
+    let recovery = match (state, valid) {
+        (STATE_EMPTY, _) => return Ok(Recovery::Clean),
+        (_, true) => {
+            let mut buffer = [0u8; 512];
+            for (i, &target) in targets[..count].iter().enumerate() {
+                device.read_sector(header_sector + 1 + i as u64, &mut buffer)?;
+                device.write_sector(base + target as u64, &buffer)?;
+            }
+            device.flush()?;
+            Recovery::Replayed { sectors: count as u32 }
+        }
+        (_, false) => Recovery::Discarded,
+    };
+    
+    let mut empty = [0u8; 512];
+    empty[0..8].copy_from_slice(JOURNAL_MAGIC);
+    empty[8..12].copy_from_slice(&STATE_EMPTY.to_le_bytes());
+    device.write_sector_kind(header_sector, &empty, SectorKind::Directory)?;
+    device.flush()?;
+    
+    Ok(recovery)
+}
+
+impl<D: BlockDeviceMut> Fat32Parser<'_, JournaledDevice<D>> {
+    /// exécute `f` dans une transaction
+    /// 
+    /// les métadonnées modifiées par `f` sont validées ensemble si `f`
+    /// réussit, et abandonnées sinon ; l'état en mémoire du parser
+    /// (boot sector, géométrie, état du volume, FSInfo, bitmap) est alors
+    /// rétabli ou relu depuis le disque.
+    pub fn transaction<T, F>(&mut self, f: F) -> Result<T, Fat32Error<D::Error>>
+    where
+        F: FnOnce(&mut Self) -> Result<T, Fat32Error<D::Error>>,
+    {
+        self.device.begin()?;
+        // le bit « sale » retiré dans la transaction est abandonné avec elle
+        let saved = (self.boot_sector, self.geometry, self.volume_flags, self.dirty, self.validation, self.device_sectors);
+        
+        match f(self) {
+            Ok(value) => {
+                self.device.commit()?;
+                Ok(value)
+            }
+            Err(e) => {
+                self.device.abort();
+                (self.boot_sector, self.geometry, self.volume_flags, self.dirty, self.validation, self.device_sectors) = saved;
+                if self.fsinfo.is_some() {
+                    self.load_fsinfo()?;
+                }
+                self.rebuild_free_bitmap()?;
+                Err(e)
+            }
+        }
+    }
+}
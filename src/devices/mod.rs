@@ -0,0 +1,12 @@
+//! dispositifs génériques construits au-dessus de BlockDevice
+
+pub mod cache;
+pub mod journal;
+#[cfg(feature = "alloc")]
+pub mod ram;
+#[cfg(feature = "alloc")]
+pub mod overlay;
+#[cfg(all(test, feature = "alloc"))]
+pub(crate) mod recording;
+#[cfg(feature = "std")]
+pub mod file;
@@ -0,0 +1,269 @@
+//! superposition copie sur écriture (feature `alloc`)
+//! 
+//! `OverlayDevice` lit un dispositif de base sans jamais l'écrire : chaque
+//! secteur écrit est placé dans une superposition, et les lectures
+//! suivantes voient cette version. la base n'a besoin que de
+//! `BlockDevice` ; une image à examiner, ouverte en lecture seule, peut
+//! ainsi subir des opérations destructives sans risque.
+//! 
+//! la superposition est gardée en mémoire, ou (feature `std`) dans un
+//! fichier annexe qui survit au programme : rouvert avec `with_sidecar`,
+//! il redonne les mêmes modifications. elle peut être abandonnée, ou
+//! recopiée sur la base si celle-ci accepte les écritures.
+//! 
+//! # Fichier annexe
+//! 
+//! ```text
+//! enregistrement i (524 octets)  numéro du secteur (u64), contenu,
+//!                                FNV-1a des 520 octets précédents (u32)
+//! ```
+//! 
+//! le fichier ne fait que grandir : chaque écriture ajoute un
+//! enregistrement, et à la réouverture le dernier enregistrement valide
+//! d'un secteur l'emporte. un enregistrement interrompu par une coupure,
+//! incomplet ou dont le checksum ne correspond pas, est ignoré : le
+//! secteur garde sa version précédente. la place des anciennes versions
+//! est rendue par `discard` et `commit`.
+
+use alloc::collections::BTreeMap;
+use alloc::vec::Vec;
+use crate::traits::block_device::{BlockDevice, BlockDeviceMut};
+use crate::utils::error::Fat32Error;
+#[cfg(feature = "std")]
+use crate::utils::{bytes, helpers::{fnv1a, FNV1A_INIT}};
+
+/// taille d'un enregistrement du fichier annexe
+#[cfg(feature = "std")]
+const RECORD_SIZE: u64 = 8 + 512 + 4;
+
+/// stockage des secteurs modifiés
+enum Store {
+    /// secteurs en mémoire, dans l'ordre de leur première écriture
+    Memory(Vec<[u8; 512]>),
+    /// fichier annexe et nombre d'enregistrements
+    #[cfg(feature = "std")]
+    Sidecar(std::fs::File, usize),
+}
+
+/// dispositif copie sur écriture au-dessus d'un dispositif de base
+pub struct OverlayDevice<D: BlockDevice> {
+    base: D,
+    /// secteur modifié vers sa position dans le stockage
+    index: BTreeMap<u64, usize>,
+    store: Store,
+}
+
+impl<D: BlockDevice> OverlayDevice<D> {
+    /// crée une superposition vide, gardée en mémoire
+    pub fn new(base: D) -> Self {
+        Self {
+            base,
+            index: BTreeMap::new(),
+            store: Store::Memory(Vec::new()),
+        }
+    }
+    
+    /// crée une superposition gardée dans le fichier annexe `file`
+    /// 
+    /// les secteurs déjà enregistrés dans le fichier sont repris, en
+    /// ignorant les enregistrements invalides. le fichier doit être ouvert
+    /// en lecture et en écriture.
+    #[cfg(feature = "std")]
+    pub fn with_sidecar(base: D, file: std::fs::File) -> std::io::Result<Self> {
+        use crate::devices::file::read_exact_at;
+        
+        // un enregistrement incomplet en fin de fichier sera recouvert
+        let records = (file.metadata()?.len() / RECORD_SIZE) as usize;
+        let mut index = BTreeMap::new();
+        let mut record = [0u8; RECORD_SIZE as usize];
+        for slot in 0..records {
+            read_exact_at(&file, &mut record, slot as u64 * RECORD_SIZE)?;
+            if let Some(sector) = parse_record(&record) {
+                index.insert(sector, slot);
+            }
+        }
+        
+        Ok(Self {
+            base,
+            index,
+            store: Store::Sidecar(file, records),
+        })
+    }
+    
+    /// retourne le dispositif de base
+    pub fn base(&self) -> &D {
+        &self.base
+    }
+    
+    /// rend le dispositif de base, sans les modifications
+    pub fn into_base(self) -> D {
+        self.base
+    }
+    
+    /// retourne le nombre de secteurs modifiés
+    pub fn changed_count(&self) -> usize {
+        self.index.len()
+    }
+    
+    /// vérifie si un secteur a été modifié
+    pub fn is_changed(&self, sector: u64) -> bool {
+        self.index.contains_key(&sector)
+    }
+    
+    /// retourne les secteurs modifiés, dans l'ordre croissant
+    pub fn changed_sectors(&self) -> impl Iterator<Item = u64> + '_ {
+        self.index.keys().copied()
+    }
+    
+    /// passe chaque secteur modifié et son contenu au callback, dans
+    /// l'ordre croissant
+    pub fn for_each_change<F>(&self, mut f: F) -> Result<(), Fat32Error<D::Error>>
+    where
+        F: FnMut(u64, &[u8; 512]) -> Result<(), Fat32Error<D::Error>>,
+    {
+        let mut buffer = [0u8; 512];
+        for (&sector, &slot) in &self.index {
+            self.load(sector, slot, &mut buffer)?;
+            f(sector, &buffer)?;
+        }
+        Ok(())
+    }
+    
+    /// abandonne toutes les modifications
+    pub fn discard(&mut self) -> Result<(), Fat32Error<D::Error>> {
+        self.index.clear();
+        match &mut self.store {
+            Store::Memory(sectors) => sectors.clear(),
+            #[cfg(feature = "std")]
+            Store::Sidecar(file, records) => {
+                file.set_len(0).map_err(|_| Fat32Error::WriteError { sector: 0 })?;
+                *records = 0;
+            }
+        }
+        Ok(())
+    }
+    
+    /// lit le secteur `sector` enregistré à la position `slot`
+    fn load(&self, sector: u64, slot: usize, buffer: &mut [u8]) -> Result<(), Fat32Error<D::Error>> {
+        match &self.store {
+            Store::Memory(sectors) => {
+                buffer.copy_from_slice(sectors.get(slot).ok_or(Fat32Error::ReadError { sector })?);
+            }
+            #[cfg(feature = "std")]
+            Store::Sidecar(file, _) => {
+                crate::devices::file::read_exact_at(file, buffer, slot as u64 * RECORD_SIZE + 8)
+                    .map_err(|_| Fat32Error::ReadError { sector })?;
+            }
+        }
+        Ok(())
+    }
+    
+    /// enregistre le nouveau contenu de `sector`
+    /// 
+    /// en mémoire, un secteur réécrit reprend sa place ; dans le fichier
+    /// annexe, le nouvel enregistrement est ajouté à la fin.
+    fn store(&mut self, sector: u64, buffer: &[u8]) -> Result<(), Fat32Error<D::Error>> {
+        let slot = match &mut self.store {
+            Store::Memory(sectors) => {
+                let slot = self.index.get(&sector).copied().unwrap_or(sectors.len());
+                if slot == sectors.len() {
+                    sectors.push([0; 512]);
+                }
+                sectors[slot].copy_from_slice(buffer);
+                slot
+            }
+            #[cfg(feature = "std")]
+            Store::Sidecar(file, records) => {
+                let slot = *records;
+                let mut record = [0u8; RECORD_SIZE as usize];
+                bytes::put_bytes(&mut record, 0, &sector.to_le_bytes());
+                bytes::put_bytes(&mut record, 8, buffer);
+                let checksum = fnv1a(FNV1A_INIT, &record[..520]);
+                bytes::put_u32(&mut record, 520, checksum);
+                crate::devices::file::write_all_at(file, &record, slot as u64 * RECORD_SIZE)
+                    .map_err(|_| Fat32Error::WriteError { sector })?;
+                *records += 1;
+                slot
+            }
+        };
+        
+        self.index.insert(sector, slot);
+        Ok(())
+    }
+}
+
+impl<D: BlockDeviceMut> OverlayDevice<D> {
+    /// recopie les modifications sur la base, puis vide la superposition
+    /// 
+    /// en cas d'erreur, la superposition est conservée : un nouvel appel
+    /// reprend la recopie.
+    pub fn commit(&mut self) -> Result<(), Fat32Error<D::Error>> {
+        let mut buffer = [0u8; 512];
+        for (&sector, &slot) in &self.index {
+            self.load(sector, slot, &mut buffer)?;
+            self.base.write_sector(sector, &buffer)?;
+        }
+        self.base.flush()?;
+        self.discard()
+    }
+}
+
+impl<D: BlockDevice> BlockDevice for OverlayDevice<D> {
+    type Error = D::Error;
+    
+    fn read_sector(&self, sector: u64, buffer: &mut [u8]) -> Result<(), Fat32Error<D::Error>> {
+        match self.index.get(&sector) {
+            Some(&slot) => self.load(sector, slot, buffer.get_mut(..512).ok_or(Fat32Error::BufferTooSmall)?),
+            None => self.base.read_sector(sector, buffer),
+        }
+    }
+    
+    // transfert direct depuis la base si aucun secteur n'a été modifié
+    fn read_sectors(&self, start: u64, buffer: &mut [u8]) -> Result<(), Fat32Error<D::Error>> {
+        if !buffer.len().is_multiple_of(512) {
+            return Err(Fat32Error::BufferTooSmall);
+        }
+        let end = start.saturating_add((buffer.len() / 512) as u64);
+        if self.index.range(start..end).next().is_none() {
+            return self.base.read_sectors(start, buffer);
+        }
+        for (i, chunk) in buffer.chunks_exact_mut(512).enumerate() {
+            self.read_sector(start + i as u64, chunk)?;
+        }
+        Ok(())
+    }
+    
+    fn sector_size(&self) -> u32 {
+        self.base.sector_size()
+    }
+    
+    fn sector_count(&self) -> Option<u64> {
+        self.base.sector_count()
+    }
+}
+
+// la base n'est jamais écrite : seule la lecture lui est demandée
+impl<D: BlockDevice> BlockDeviceMut for OverlayDevice<D> {
+    fn write_sector(&mut self, sector: u64, buffer: &[u8]) -> Result<(), Fat32Error<D::Error>> {
+        if self.base.sector_count().is_some_and(|count| sector >= count) {
+            return Err(Fat32Error::InvalidSector { sector });
+        }
+        self.store(sector, buffer.get(..512).ok_or(Fat32Error::BufferTooSmall)?)
+    }
+    
+    fn flush(&mut self) -> Result<(), Fat32Error<D::Error>> {
+        match &self.store {
+            Store::Memory(_) => Ok(()),
+            #[cfg(feature = "std")]
+            Store::Sidecar(file, _) => file.sync_data().map_err(|_| Fat32Error::WriteError { sector: 0 }),
+        }
+    }
+}
+
+/// numéro du secteur d'un enregistrement du fichier annexe, s'il est
+/// valide
+#[cfg(feature = "std")]
+fn parse_record(record: &[u8; RECORD_SIZE as usize]) -> Option<u64> {
+    (fnv1a(FNV1A_INIT, &record[..520]) == bytes::u32_at(record, 520))
+        .then(|| u64::from_le_bytes(bytes::array_at(record, 0)))
+}
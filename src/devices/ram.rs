@@ -0,0 +1,130 @@
+//! dispositif en mémoire (feature `alloc`)
+//! 
+//! `RamBlockDevice` garde l'image dans un `Vec<u8>` qui s'agrandit quand
+//! on écrit au-delà de sa fin : un outil ou un test y construit une image
+//! secteur par secteur, puis la récupère d'un bloc avec `into_vec`.
+//! sans limite fixée, une écriture refusée par l'allocateur ou qui
+//! porterait l'image au-delà de `isize::MAX` octets échoue avec
+//! `InvalidSector`.
+
+use alloc::vec::Vec;
+use core::convert::Infallible;
+use crate::traits::block_device::{BlockDevice, BlockDeviceMut};
+use crate::utils::error::Fat32Error;
+
+/// image disque en mémoire
+#[derive(Debug, Clone, Default)]
+pub struct RamBlockDevice {
+    data: Vec<u8>,
+    /// taille maximale en secteurs, sans limite si `None`
+    max_sectors: Option<u64>,
+}
+
+impl RamBlockDevice {
+    /// crée un dispositif vide
+    pub fn new() -> Self {
+        Self::default()
+    }
+    
+    /// crée un dispositif de `sectors` secteurs à zéro
+    pub fn with_sectors(sectors: usize) -> Self {
+        Self::from_vec(alloc::vec![0; sectors * 512])
+    }
+    
+    /// utilise une image existante
+    /// 
+    /// un dernier secteur incomplet est complété par des zéros.
+    pub fn from_vec(mut data: Vec<u8>) -> Self {
+        data.resize(data.len().next_multiple_of(512), 0);
+        Self {
+            data,
+            max_sectors: None,
+        }
+    }
+    
+    /// refuse les écritures au-delà de `max_sectors` secteurs
+    pub fn with_max_sectors(mut self, max_sectors: u64) -> Self {
+        self.max_sectors = Some(max_sectors);
+        self
+    }
+    
+    /// retourne le contenu de l'image
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.data
+    }
+    
+    /// rend le contenu de l'image
+    pub fn into_vec(self) -> Vec<u8> {
+        self.data
+    }
+    
+    /// position de `len` octets à partir de `sector`, si elle tient dans
+    /// `limit` octets
+    fn range(sector: u64, len: usize, limit: usize) -> Result<core::ops::Range<usize>, Fat32Error> {
+        usize::try_from(sector)
+            .ok()
+            .and_then(|sector| sector.checked_mul(512))
+            .and_then(|start| Some(start..start.checked_add(len)?))
+            .filter(|range| range.end <= limit)
+            .ok_or(Fat32Error::InvalidSector { sector })
+    }
+}
+
+impl From<Vec<u8>> for RamBlockDevice {
+    fn from(data: Vec<u8>) -> Self {
+        Self::from_vec(data)
+    }
+}
+
+impl BlockDevice for RamBlockDevice {
+    type Error = Infallible;
+    
+    fn read_sector(&self, sector: u64, buffer: &mut [u8]) -> Result<(), Fat32Error> {
+        if buffer.len() < 512 {
+            return Err(Fat32Error::BufferTooSmall);
+        }
+        let range = Self::range(sector, 512, self.data.len())?;
+        buffer[..512].copy_from_slice(&self.data[range]);
+        Ok(())
+    }
+    
+    fn read_sectors(&self, start: u64, buffer: &mut [u8]) -> Result<(), Fat32Error> {
+        if !buffer.len().is_multiple_of(512) {
+            return Err(Fat32Error::BufferTooSmall);
+        }
+        let range = Self::range(start, buffer.len(), self.data.len())?;
+        buffer.copy_from_slice(&self.data[range]);
+        Ok(())
+    }
+    
+    fn sector_count(&self) -> Option<u64> {
+        Some((self.data.len() / 512) as u64)
+    }
+}
+
+impl BlockDeviceMut for RamBlockDevice {
+    fn write_sector(&mut self, sector: u64, buffer: &[u8]) -> Result<(), Fat32Error> {
+        let buffer = buffer.get(..512).ok_or(Fat32Error::BufferTooSmall)?;
+        self.write_sectors(sector, buffer)
+    }
+    
+    // agrandit l'image jusqu'au dernier secteur écrit
+    fn write_sectors(&mut self, start: u64, buffer: &[u8]) -> Result<(), Fat32Error> {
+        if !buffer.len().is_multiple_of(512) {
+            return Err(Fat32Error::BufferTooSmall);
+        }
+        let limit = match self.max_sectors {
+            Some(max) => usize::try_from(max).unwrap_or(usize::MAX).saturating_mul(512),
+            None => isize::MAX as usize,
+        };
+        let range = Self::range(start, buffer.len(), limit)?;
+        if range.end > self.data.len() {
+            self.data
+                .try_reserve(range.end - self.data.len())
+                .map_err(|_| Fat32Error::InvalidSector { sector: start })?;
+            self.data.resize(range.end, 0);
+        }
+        self.data[range].copy_from_slice(buffer);
+        Ok(())
+    }
+}
@@ -0,0 +1,167 @@
+//! enregistrement des écritures et pannes simulées, pour les tests
+//! 
+//! `RecordingDevice` transmet tout à un dispositif sous-jacent en notant
+//! chaque secteur écrit, dans l'ordre et avec son contenu, ainsi que les
+//! transferts de plusieurs secteurs. il peut aussi faire échouer les
+//! écritures vers un secteur donné, pour simuler une panne.
+//! 
+//! les écritures notées se rejouent sur une copie de l'image d'origine :
+//! en rejouant chaque préfixe, un outil vérifie l'état du volume après une
+//! coupure à n'importe quel moment.
+
+use alloc::vec::Vec;
+use core::cell::{Ref, RefCell};
+use crate::traits::block_device::{BlockDevice, BlockDeviceMut, SectorKind};
+use crate::utils::error::Fat32Error;
+
+/// secteur écrit et son contenu
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RecordedWrite {
+    pub sector: u64,
+    pub kind: SectorKind,
+    pub data: [u8; 512],
+}
+
+/// dispositif qui note ses écritures et peut simuler des pannes
+pub struct RecordingDevice<D: BlockDevice> {
+    inner: D,
+    writes: Vec<RecordedWrite>,
+    /// premier secteur et longueur des lectures de plusieurs secteurs
+    read_transfers: RefCell<Vec<(u64, usize)>>,
+    /// premier secteur et longueur des écritures de plusieurs secteurs
+    write_transfers: Vec<(u64, usize)>,
+    /// les écritures vers ce secteur échouent
+    fail_sector: Option<u64>,
+}
+
+impl<D: BlockDevice> RecordingDevice<D> {
+    /// enregistre les écritures destinées à `inner`
+    pub fn new(inner: D) -> Self {
+        Self {
+            inner,
+            writes: Vec::new(),
+            read_transfers: RefCell::new(Vec::new()),
+            write_transfers: Vec::new(),
+            fail_sector: None,
+        }
+    }
+    
+    /// fait échouer les écritures vers `sector`
+    pub fn fail_at(&mut self, sector: u64) {
+        self.fail_sector = Some(sector);
+    }
+    
+    /// retire les pannes simulées
+    pub fn clear_failures(&mut self) {
+        self.fail_sector = None;
+    }
+    
+    /// retourne les secteurs écrits, dans l'ordre
+    pub fn writes(&self) -> &[RecordedWrite] {
+        &self.writes
+    }
+    
+    /// retourne les numéros des secteurs écrits, dans l'ordre
+    pub fn written_sectors(&self) -> Vec<u64> {
+        self.writes.iter().map(|write| write.sector).collect()
+    }
+    
+    /// retourne les lectures de plusieurs secteurs (début, longueur)
+    pub fn read_transfers(&self) -> Ref<'_, Vec<(u64, usize)>> {
+        self.read_transfers.borrow()
+    }
+    
+    /// retourne les écritures de plusieurs secteurs (début, longueur)
+    pub fn write_transfers(&self) -> &[(u64, usize)] {
+        &self.write_transfers
+    }
+    
+    /// oublie les écritures et transferts notés
+    pub fn clear(&mut self) {
+        self.writes.clear();
+        self.read_transfers.get_mut().clear();
+        self.write_transfers.clear();
+    }
+    
+    /// vérifie qu'aucune panne ne touche `count` secteurs à partir de
+    /// `start` ; rien n'est écrit sinon
+    fn check_failures(&self, start: u64, count: usize) -> Result<(), Fat32Error<D::Error>> {
+        if let Some(sector) = self.fail_sector.filter(|&sector| sector >= start && sector - start < count as u64) {
+            return Err(Fat32Error::WriteError { sector });
+        }
+        Ok(())
+    }
+    
+    fn record(&mut self, start: u64, buffer: &[u8], kind: SectorKind) {
+        for (i, chunk) in buffer.chunks_exact(512).enumerate() {
+            let mut data = [0u8; 512];
+            data.copy_from_slice(chunk);
+            self.writes.push(RecordedWrite {
+                sector: start + i as u64,
+                kind,
+                data,
+            });
+        }
+    }
+    
+    /// applique les `count` premières écritures notées sur `target`
+    /// 
+    /// `target` est en général une copie de l'image d'origine : le
+    /// résultat est l'état du support après une coupure survenue à ce
+    /// moment.
+    pub fn replay<T: BlockDeviceMut>(&self, count: usize, target: &mut T) -> Result<(), Fat32Error<T::Error>> {
+        for write in &self.writes[..count.min(self.writes.len())] {
+            target.write_sector(write.sector, &write.data)?;
+        }
+        Ok(())
+    }
+}
+
+impl<D: BlockDevice> BlockDevice for RecordingDevice<D> {
+    type Error = D::Error;
+    
+    fn read_sector(&self, sector: u64, buffer: &mut [u8]) -> Result<(), Fat32Error<D::Error>> {
+        self.inner.read_sector(sector, buffer)
+    }
+    
+    fn read_sectors(&self, start: u64, buffer: &mut [u8]) -> Result<(), Fat32Error<D::Error>> {
+        self.read_transfers.borrow_mut().push((start, buffer.len() / 512));
+        self.inner.read_sectors(start, buffer)
+    }
+    
+    fn sector_size(&self) -> u32 {
+        self.inner.sector_size()
+    }
+    
+    fn sector_count(&self) -> Option<u64> {
+        self.inner.sector_count()
+    }
+}
+
+impl<D: BlockDeviceMut> BlockDeviceMut for RecordingDevice<D> {
+    fn write_sector(&mut self, sector: u64, buffer: &[u8]) -> Result<(), Fat32Error<D::Error>> {
+        self.write_sector_kind(sector, buffer, SectorKind::Data)
+    }
+    
+    fn write_sector_kind(&mut self, sector: u64, buffer: &[u8], kind: SectorKind) -> Result<(), Fat32Error<D::Error>> {
+        self.check_failures(sector, 1)?;
+        self.inner.write_sector_kind(sector, buffer, kind)?;
+        self.record(sector, &buffer[..512], kind);
+        Ok(())
+    }
+    
+    fn write_sectors(&mut self, start: u64, buffer: &[u8]) -> Result<(), Fat32Error<D::Error>> {
+        if !buffer.len().is_multiple_of(512) {
+            return Err(Fat32Error::BufferTooSmall);
+        }
+        self.check_failures(start, buffer.len() / 512)?;
+        self.write_transfers.push((start, buffer.len() / 512));
+        self.inner.write_sectors(start, buffer)?;
+        self.record(start, buffer, SectorKind::Data);
+        Ok(())
+    }
+    
+    fn flush(&mut self) -> Result<(), Fat32Error<D::Error>> {
+        self.inner.flush()
+    }
+}
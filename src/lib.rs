@@ -0,0 +1,58 @@
+//! parser FAT32 en environnement no_std
+//! 
+//! ce crate fournit les outils pour parser un système de fichiers FAT32
+//! sans dépendre de la bibliothèque standard.
+//! 
+//! # Features
+//! 
+//! - `alloc` : listes et noms alloués sur le tas (`Vec`, `String`),
+//!   dispositif en mémoire et superposition copie sur écriture
+//! - `std` : dispositifs sur fichier et `std::error::Error` (inclut `alloc`)
+//! 
+//! # Modules principaux
+//! 
+//! - [`operations::parser`] : structure principale Fat32Parser
+//! - [`structures::boot_sector`] : structure du boot sector
+//! - [`structures::dir_entry`] : entrées de répertoire
+//! - [`traits::block_device`] : trait pour les dispositifs de stockage
+//! - [`operations::async_parser`] : parser asynchrone
+//! - [`devices::cache`] : cache de secteurs
+
+#![no_std]
+
+#[cfg(feature = "alloc")]
+extern crate alloc;
+#[cfg(feature = "std")]
+extern crate std;
+
+// structures de données FAT32
+pub mod structures;
+
+// opérations sur le système de fichiers
+pub mod operations;
+
+// traits
+pub mod traits;
+
+// dispositifs génériques
+pub mod devices;
+
+// utilitaires
+pub mod utils;
+
+// ré-exports pour compatibilité
+pub use structures::boot_sector;
+pub use structures::dir_entry;
+pub use structures::fsinfo;
+pub use operations::file_info;
+pub use operations::file_ops;
+pub use operations::parser;
+pub use traits::block_device;
+pub use utils::constants;
+pub use utils::error;
+pub use utils::fat;
+pub use utils::helpers as utils_helpers;
+pub use utils::validator;
+
+#[cfg(all(test, feature = "alloc"))]
+mod tests;
@@ -0,0 +1,306 @@
+//! stratégies d'allocation de clusters
+//! 
+//! `FirstFit` reprend le comportement historique (premier cluster libre).
+//! `NextFit` reprend la recherche là où la précédente s'est arrêtée, à
+//! partir de `next_free` de FSInfo. `ContiguousFit` prolonge les chaînes
+//! sur place et place les nouvelles dans le plus petit trou suffisant.
+//! `RoundRobin` répartit les allocations sur des zones successives du
+//! volume pour étaler l'usure d'une mémoire flash sans contrôleur.
+
+use crate::operations::parser::Fat32Parser;
+use crate::operations::steps::{self, FreeRunSearch};
+use crate::traits::block_device::{BlockDevice, BlockDeviceMut};
+use crate::traits::cluster_allocator::ClusterAllocator;
+use crate::utils::error::Fat32Error;
+use crate::utils::fat;
+
+/// premier emplacement libre du volume
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct FirstFit;
+
+/// reprend après la dernière allocation
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct NextFit {
+    /// prochain cluster à examiner, 0 tant que FSInfo n'a pas été consulté
+    cursor: u32,
+}
+
+/// favorise les suites contiguës
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ContiguousFit;
+
+/// répartition tournante sur des zones du volume
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RoundRobin {
+    zones: u32,
+    next_zone: u32,
+}
+
+/// stratégie intégrée utilisée par `allocate_cluster`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AllocationStrategy {
+    FirstFit(FirstFit),
+    NextFit(NextFit),
+    ContiguousFit(ContiguousFit),
+    RoundRobin(RoundRobin),
+}
+
+impl Default for AllocationStrategy {
+    fn default() -> Self {
+        AllocationStrategy::FirstFit(FirstFit)
+    }
+}
+
+impl NextFit {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl RoundRobin {
+    /// crée une répartition sur `zones` zones (au moins une)
+    pub fn new(zones: u32) -> Self {
+        Self {
+            zones: zones.max(1),
+            next_zone: 0,
+        }
+    }
+    
+    /// premier cluster d'une zone
+    fn zone_start(&self, max_cluster: u32, zone: u32) -> u32 {
+        let clusters = max_cluster - 1;
+        2 + (clusters as u64 * zone as u64 / self.zones as u64) as u32
+    }
+}
+
+impl ClusterAllocator for FirstFit {
+    fn find_run<D: BlockDevice>(
+        &mut self,
+        parser: &Fat32Parser<D>,
+        _previous: Option<u32>,
+        length: u32,
+    ) -> Result<Option<u32>, Fat32Error<D::Error>> {
+        parser.find_free_run_between(2, parser.max_cluster(), length)
+    }
+}
+
+impl ClusterAllocator for NextFit {
+    fn find_run<D: BlockDevice>(
+        &mut self,
+        parser: &Fat32Parser<D>,
+        _previous: Option<u32>,
+        length: u32,
+    ) -> Result<Option<u32>, Fat32Error<D::Error>> {
+        if self.cursor < 2 {
+            self.cursor = parser.fsinfo()
+                .and_then(|fsinfo| fsinfo.next_free_cluster())
+                .unwrap_or(2);
+        }
+        parser.find_free_run_from(self.cursor, length)
+    }
+    
+    fn allocated(&mut self, first: u32, length: u32) {
+        self.cursor = first.saturating_add(length);
+    }
+}
+
+impl ClusterAllocator for ContiguousFit {
+    fn find_run<D: BlockDevice>(
+        &mut self,
+        parser: &Fat32Parser<D>,
+        previous: Option<u32>,
+        length: u32,
+    ) -> Result<Option<u32>, Fat32Error<D::Error>> {
+        let max_cluster = parser.max_cluster();
+        
+        // prolonge la chaîne sans trou si possible
+        if let Some(next) = previous.and_then(|previous| previous.checked_add(1)) {
+            let last = next.checked_add(length.saturating_sub(1));
+            if let Some(last) = last.filter(|&last| next >= 2 && last <= max_cluster) {
+                if parser.find_free_run_between(next, last, length)? == Some(next) {
+                    return Ok(Some(next));
+                }
+            }
+        }
+        
+        // sinon le plus petit trou suffisant, pour garder les grands libres
+        let mut best: Option<(u32, u32)> = None;
+        let mut run_start = 0;
+        let mut run_length = 0;
+        
+        for cluster in 2..=max_cluster + 1 {
+            if cluster <= max_cluster && parser.is_cluster_free(cluster)? {
+                if run_length == 0 {
+                    run_start = cluster;
+                }
+                run_length += 1;
+                continue;
+            }
+            
+            if run_length >= length && best.is_none_or(|(_, best_length)| run_length < best_length) {
+                best = Some((run_start, run_length));
+                if run_length == length {
+                    break;
+                }
+            }
+            run_length = 0;
+        }
+        
+        Ok(best.map(|(start, _)| start))
+    }
+}
+
+impl ClusterAllocator for RoundRobin {
+    fn find_run<D: BlockDevice>(
+        &mut self,
+        parser: &Fat32Parser<D>,
+        _previous: Option<u32>,
+        length: u32,
+    ) -> Result<Option<u32>, Fat32Error<D::Error>> {
+        let start = self.zone_start(parser.max_cluster(), self.next_zone % self.zones);
+        parser.find_free_run_from(start, length)
+    }
+    
+    fn allocated(&mut self, _first: u32, _length: u32) {
+        self.next_zone = (self.next_zone + 1) % self.zones;
+    }
+}
+
+impl ClusterAllocator for AllocationStrategy {
+    fn find_run<D: BlockDevice>(
+        &mut self,
+        parser: &Fat32Parser<D>,
+        previous: Option<u32>,
+        length: u32,
+    ) -> Result<Option<u32>, Fat32Error<D::Error>> {
+        match self {
+            AllocationStrategy::FirstFit(s) => s.find_run(parser, previous, length),
+            AllocationStrategy::NextFit(s) => s.find_run(parser, previous, length),
+            AllocationStrategy::ContiguousFit(s) => s.find_run(parser, previous, length),
+            AllocationStrategy::RoundRobin(s) => s.find_run(parser, previous, length),
+        }
+    }
+    
+    fn allocated(&mut self, first: u32, length: u32) {
+        match self {
+            AllocationStrategy::FirstFit(s) => s.allocated(first, length),
+            AllocationStrategy::NextFit(s) => s.allocated(first, length),
+            AllocationStrategy::ContiguousFit(s) => s.allocated(first, length),
+            AllocationStrategy::RoundRobin(s) => s.allocated(first, length),
+        }
+    }
+}
+
+impl<D: BlockDevice> Fat32Parser<'_, D> {
+    /// retourne la stratégie utilisée par `allocate_cluster`
+    pub fn allocation_strategy(&self) -> AllocationStrategy {
+        self.allocator
+    }
+    
+    /// change la stratégie utilisée par `allocate_cluster`
+    pub fn set_allocation_strategy(&mut self, strategy: AllocationStrategy) {
+        self.allocator = strategy;
+    }
+    
+    /// vérifie si un cluster est libre, via le bitmap s'il est attaché
+    pub fn is_cluster_free(&self, cluster: u32) -> Result<bool, Fat32Error<D::Error>> {
+        match self.free_bitmap.as_ref() {
+            Some(bitmap) => Ok(bitmap.is_free(cluster)),
+            None => Ok(fat::is_free(self.read_fat_entry(cluster)?)),
+        }
+    }
+    
+    /// cherche `length` clusters libres consécutifs entre `first` et `last`
+    pub fn find_free_run_between(&self, first: u32, last: u32, length: u32) -> Result<Option<u32>, Fat32Error<D::Error>> {
+        let mut search = FreeRunSearch::new(first, last.min(self.max_cluster()), length);
+        
+        while let Some(cluster) = search.next_cluster() {
+            if let Some(found) = search.feed(self.is_cluster_free(cluster)?) {
+                return Ok(Some(found));
+            }
+        }
+        
+        Ok(None)
+    }
+    
+    /// cherche une suite libre à partir de `start`, en repartant du début
+    /// du volume si besoin
+    pub(crate) fn find_free_run_from(&self, start: u32, length: u32) -> Result<Option<u32>, Fat32Error<D::Error>> {
+        let max_cluster = self.max_cluster();
+        let start = if start < 2 || start > max_cluster { 2 } else { start };
+        
+        if let Some(found) = self.find_free_run_between(start, max_cluster, length)? {
+            return Ok(Some(found));
+        }
+        // une suite à cheval sur `start` est trouvée au second passage
+        self.find_free_run_between(2, start.saturating_add(length.saturating_sub(1)).min(max_cluster), length)
+    }
+}
+
+impl<D: BlockDeviceMut> Fat32Parser<'_, D> {
+    /// alloue `length` clusters contigus avec la stratégie du parser
+    /// 
+    /// la suite est chaînée dans la FAT et rattachée à `prev_cluster` s'il
+    /// est fourni. retourne son premier cluster.
+    pub fn allocate_run(&mut self, prev_cluster: Option<u32>, length: u32) -> Result<u32, Fat32Error<D::Error>> {
+        let mut allocator = self.allocator;
+        let result = self.allocate_run_with(&mut allocator, prev_cluster, length);
+        self.allocator = allocator;
+        result
+    }
+    
+    /// alloue un cluster avec une stratégie fournie par l'appelant
+    pub fn allocate_cluster_with<A: ClusterAllocator>(
+        &mut self,
+        allocator: &mut A,
+        prev_cluster: Option<u32>,
+    ) -> Result<u32, Fat32Error<D::Error>> {
+        self.allocate_run_with(allocator, prev_cluster, 1)
+    }
+    
+    /// alloue `length` clusters contigus avec une stratégie fournie par
+    /// l'appelant
+    /// 
+    /// retourne `DiskFull` si aucune suite assez longue n'est libre, et
+    /// `InvalidCluster` si `prev_cluster` est hors du volume ou si la
+    /// stratégie propose une suite hors du volume ou dont un cluster est
+    /// occupé ; rien n'est alors écrit.
+    pub fn allocate_run_with<A: ClusterAllocator>(
+        &mut self,
+        allocator: &mut A,
+        prev_cluster: Option<u32>,
+        length: u32,
+    ) -> Result<u32, Fat32Error<D::Error>> {
+        if length == 0 {
+            return Err(Fat32Error::InvalidCluster { cluster: 0 });
+        }
+        if let Some(previous) = prev_cluster.filter(|&previous| previous < 2 || previous > self.max_cluster()) {
+            return Err(Fat32Error::InvalidCluster { cluster: previous });
+        }
+        #[cfg(feature = "alloc")]
+        self.enable_free_bitmap()?;
+        
+        let first = allocator.find_run(self, prev_cluster, length)?.ok_or(Fat32Error::DiskFull)?;
+        let last = steps::check_run(first, length, self.max_cluster())?;
+        // une stratégie externe peut se tromper : la suite est vérifiée
+        // avant d'être chaînée
+        for cluster in first..=last {
+            if !self.is_cluster_free(cluster)? {
+                return Err(Fat32Error::InvalidCluster { cluster });
+            }
+        }
+        
+        for (cluster, next) in steps::run_links(first, last, prev_cluster) {
+            self.write_fat_entry(cluster, next)?;
+        }
+        
+        allocator.allocated(first, length);
+        // écrit dans FSInfo au prochain flush
+        let next_free = if last < self.max_cluster() { last + 1 } else { 2 };
+        if let Some(fsinfo) = self.fsinfo.as_mut() {
+            fsinfo.next_free = next_free;
+        }
+        
+        Ok(first)
+    }
+}
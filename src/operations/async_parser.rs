@@ -0,0 +1,376 @@
+//! parser FAT32 asynchrone
+//! 
+//! `AsyncFat32Parser` reprend les opérations courantes de `Fat32Parser`
+//! (FAT, clusters, fichiers, répertoires) au-dessus d'un
+//! `AsyncBlockDevice`. seules les entrées/sorties diffèrent : le suivi
+//! des chaînes, la lecture par suites contiguës, le découpage des
+//! transferts, l'allocation, l'état du volume et le décodage des secteurs
+//! sont les étapes de `operations::steps`, pilotées aussi par la version
+//! synchrone.
+
+use crate::operations::directory::{visit_sector, EntryLocation, Visit};
+use crate::operations::steps::{
+    self, ChainWalk, ClusterHead, DirStep, DirWalk, FileRead, FileWrite, FreeRunSearch, HeadPart, ReadStep, WriteStep,
+};
+use crate::operations::volume_state::VolumeFlags;
+use crate::structures::boot_sector::BootSector;
+use crate::structures::geometry::Geometry;
+use crate::structures::dir_entry::DirEntry;
+use crate::traits::async_block_device::{AsyncBlockDevice, AsyncBlockDeviceMut};
+use crate::traits::block_device::SectorKind;
+use crate::utils::error::Fat32Error;
+use crate::utils::fat;
+use crate::utils::validator::{MountOptions, ValidationReport};
+
+/// parser FAT32 asynchrone
+pub struct AsyncFat32Parser<D: AsyncBlockDevice> {
+    device: D,
+    boot_sector: BootSector,
+    geometry: Geometry,
+    volume_flags: VolumeFlags,
+    dirty: bool,
+    partition_start: u64,
+    validation: ValidationReport,
+    device_sectors: Option<u64>,
+}
+
+impl<D: AsyncBlockDevice> AsyncFat32Parser<D> {
+    /// crée un nouveau parser pour un volume commençant au secteur 0
+    pub async fn new(device: D) -> Result<Self, Fat32Error<D::Error>> {
+        Self::new_at(device, 0).await
+    }
+    
+    /// crée un parser pour un volume commençant au secteur
+    /// `partition_start` du dispositif
+    pub async fn new_at(device: D, partition_start: u64) -> Result<Self, Fat32Error<D::Error>> {
+        Self::mount_at(device, partition_start, MountOptions::default()).await
+    }
+    
+    /// monte le volume commençant au secteur `partition_start`, voir
+    /// `Fat32Parser::mount_at`
+    pub async fn mount_at(device: D, partition_start: u64, options: MountOptions) -> Result<Self, Fat32Error<D::Error>> {
+        let mut buffer = [0u8; 512];
+        device.read_sector(partition_start, &mut buffer).await?;
+        let mount = steps::mount(&buffer, partition_start, device.sector_count(), options)?;
+        
+        let geometry = mount.boot_sector.geometry();
+        device.read_sector(partition_start + geometry.fat_start as u64, &mut buffer).await?;
+        
+        Ok(Self {
+            device,
+            boot_sector: mount.boot_sector,
+            geometry,
+            volume_flags: VolumeFlags::from_fat_entry(steps::fat1_entry(&buffer)),
+            dirty: false,
+            partition_start,
+            validation: mount.validation,
+            device_sectors: mount.device_sectors,
+        })
+    }
+    
+    /// retourne le boot sector
+    pub fn boot_sector(&self) -> &BootSector {
+        &self.boot_sector
+    }
+    
+    /// retourne la géométrie du volume
+    pub fn geometry(&self) -> &Geometry {
+        &self.geometry
+    }
+    
+    /// retourne le premier secteur du volume sur le dispositif
+    pub fn partition_start(&self) -> u64 {
+        self.partition_start
+    }
+    
+    /// retourne l'état du volume lu au montage
+    pub fn volume_flags(&self) -> VolumeFlags {
+        self.volume_flags
+    }
+    
+    /// retourne les problèmes relevés dans le boot sector au montage
+    pub fn validation(&self) -> &ValidationReport {
+        &self.validation
+    }
+    
+    /// retourne le nombre de secteurs disponibles sur le dispositif à
+    /// partir du début du volume, s'il est connu
+    pub fn device_sectors(&self) -> Option<u64> {
+        self.device_sectors
+    }
+    
+    /// retourne le plus grand numéro de cluster de données valide
+    pub fn max_cluster(&self) -> u32 {
+        self.geometry.max_cluster()
+    }
+    
+    /// rend le dispositif sous-jacent
+    pub fn into_inner(self) -> D {
+        self.device
+    }
+    
+    /// lit une entrée de la FAT
+    pub async fn read_fat_entry(&self, cluster: u32) -> Result<u32, Fat32Error<D::Error>> {
+        let (sector, offset) = self.geometry.fat_entry_position(cluster)
+            .ok_or(Fat32Error::InvalidCluster { cluster })?;
+        let mut buffer = [0u8; 512];
+        self.read_sector(sector, &mut buffer).await?;
+        
+        Ok(fat::read_entry(&buffer, offset))
+    }
+    
+    /// lit un cluster complet
+    pub async fn read_cluster(&self, cluster: u32, buffer: &mut [u8]) -> Result<(), Fat32Error<D::Error>> {
+        self.read_run(cluster, 1, buffer).await
+    }
+    
+    /// lit `count` clusters contigus en un seul transfert
+    pub async fn read_run(&self, first_cluster: u32, count: u32, buffer: &mut [u8]) -> Result<(), Fat32Error<D::Error>> {
+        let (first_sector, bytes) = steps::read_run_transfer(&self.geometry, first_cluster, count, buffer.len())?;
+        self.read_sectors(first_sector, &mut buffer[..bytes]).await
+    }
+    
+    /// lit le début d'un cluster, `buffer` étant plus court qu'un cluster
+    async fn read_cluster_head(&self, cluster: u32, buffer: &mut [u8]) -> Result<(), Fat32Error<D::Error>> {
+        for part in ClusterHead::read(&self.geometry, cluster, buffer.len())? {
+            match part {
+                HeadPart::Sectors { sector, range } => self.read_sectors(sector, &mut buffer[range]).await?,
+                HeadPart::Partial { sector, range } => {
+                    let mut data = [0u8; 512];
+                    self.read_sector(sector, &mut data).await?;
+                    buffer[range.clone()].copy_from_slice(&data[..range.len()]);
+                }
+            }
+        }
+        Ok(())
+    }
+    
+    /// lit un fichier en suivant la chaîne de clusters
+    /// 
+    /// un fichier vide (cluster 0) donne 0 octet ; un cluster hors du
+    /// volume en cours de chaîne donne `BadChain`.
+    pub async fn read_file(&self, start_cluster: u32, buffer: &mut [u8]) -> Result<usize, Fat32Error<D::Error>> {
+        let mut read = FileRead::new(start_cluster, buffer.len(), self.boot_sector.cluster_size(), self.max_cluster());
+        let mut entry = None;
+        
+        loop {
+            match read.step(entry.take())? {
+                ReadStep::Fat(cluster) => entry = Some(self.read_fat_entry(cluster).await?),
+                ReadStep::Run { first, count, range } => self.read_run(first, count, &mut buffer[range]).await?,
+                ReadStep::Head { cluster, range } => self.read_cluster_head(cluster, &mut buffer[range]).await?,
+                ReadStep::Done(bytes) => return Ok(bytes),
+            }
+        }
+    }
+    
+    /// parcourt les entrées d'un répertoire jusqu'au marqueur de fin
+    /// 
+    /// même contrat que `Fat32Parser::walk_dir` ; le callback reste
+    /// synchrone, seules les lectures de secteurs sont attendues.
+    pub async fn walk_dir<F>(&self, dir_cluster: u32, f: &mut F) -> Result<Visit, Fat32Error<D::Error>>
+    where
+        F: FnMut(&DirEntry, EntryLocation) -> Result<Visit, Fat32Error<D::Error>>,
+    {
+        let mut walk = DirWalk::new(dir_cluster, &self.geometry);
+        let mut buffer = [0u8; 512];
+        let mut entry = None;
+        
+        loop {
+            match walk.step(entry.take())? {
+                DirStep::Sector { cluster, sector } => {
+                    self.read_sector(sector, &mut buffer).await?;
+                    if let Some(visit) = visit_sector(&buffer, cluster, sector, true, f)? {
+                        return Ok(visit);
+                    }
+                }
+                DirStep::Fat(cluster) => entry = Some(self.read_fat_entry(cluster).await?),
+                DirStep::Done => return Ok(Visit::Continue),
+            }
+        }
+    }
+    
+    /// lit l'entrée située à une position donnée
+    pub async fn read_dir_entry(&self, location: EntryLocation) -> Result<DirEntry, Fat32Error<D::Error>> {
+        let mut buffer = [0u8; 512];
+        self.read_sector(location.sector, &mut buffer).await?;
+        steps::entry_at(&buffer, location.index)
+    }
+    
+    async fn read_sector(&self, sector: u32, buffer: &mut [u8]) -> Result<(), Fat32Error<D::Error>> {
+        self.device.read_sector(self.partition_start + sector as u64, buffer).await
+    }
+    
+    async fn read_sectors(&self, start: u32, buffer: &mut [u8]) -> Result<(), Fat32Error<D::Error>> {
+        self.device.read_sectors(self.partition_start + start as u64, buffer).await
+    }
+}
+
+// opérations qui modifient le volume
+impl<D: AsyncBlockDeviceMut> AsyncFat32Parser<D> {
+    /// écrit sur le support les données en attente
+    /// 
+    /// le volume est remis propre s'il l'était au montage.
+    pub async fn flush(&mut self) -> Result<(), Fat32Error<D::Error>> {
+        self.device.flush().await?;
+        if let Some(patch) = steps::clean_patch(self.dirty, self.volume_flags) {
+            self.update_fat1(patch).await?;
+            self.dirty = false;
+        }
+        self.device.flush().await
+    }
+    
+    /// écrit une entrée dans la FAT
+    pub async fn write_fat_entry(&mut self, cluster: u32, value: u32) -> Result<(), Fat32Error<D::Error>> {
+        let (fat_sector, offset) = self.geometry.fat_entry_position(cluster)
+            .ok_or(Fat32Error::InvalidCluster { cluster })?;
+        self.mark_dirty().await?;
+        
+        let mut buffer = [0u8; 512];
+        self.read_sector(fat_sector, &mut buffer).await?;
+        
+        fat::write_entry(&mut buffer, offset, value);
+        self.write_sector_kind(fat_sector, &buffer, SectorKind::Fat).await
+    }
+    
+    /// alloue le premier cluster libre et le chaîne après `prev_cluster`
+    /// 
+    /// retourne `DiskFull` si aucun cluster n'est libre.
+    pub async fn allocate_cluster(&mut self, prev_cluster: Option<u32>) -> Result<u32, Fat32Error<D::Error>> {
+        self.allocate_run(prev_cluster, 1).await
+    }
+    
+    /// alloue les `length` premiers clusters libres consécutifs, comme la
+    /// stratégie par défaut de `Fat32Parser`
+    /// 
+    /// la suite est chaînée dans la FAT et rattachée à `prev_cluster` s'il
+    /// est fourni ; retourne `DiskFull` si aucune suite assez longue n'est
+    /// libre.
+    pub async fn allocate_run(&mut self, prev_cluster: Option<u32>, length: u32) -> Result<u32, Fat32Error<D::Error>> {
+        if length == 0 {
+            return Err(Fat32Error::InvalidCluster { cluster: 0 });
+        }
+        let mut search = FreeRunSearch::new(2, self.max_cluster(), length);
+        let mut found = None;
+        while let (None, Some(cluster)) = (found, search.next_cluster()) {
+            found = search.feed(fat::is_free(self.read_fat_entry(cluster).await?));
+        }
+        
+        let first = found.ok_or(Fat32Error::DiskFull)?;
+        let last = steps::check_run(first, length, self.max_cluster())?;
+        for (cluster, next) in steps::run_links(first, last, prev_cluster) {
+            self.write_fat_entry(cluster, next).await?;
+        }
+        Ok(first)
+    }
+    
+    /// libère une chaîne de clusters
+    pub async fn free_cluster_chain(&mut self, start_cluster: u32) -> Result<(), Fat32Error<D::Error>> {
+        let mut walk = ChainWalk::releasing(start_cluster, self.max_cluster());
+        
+        while let Some(cluster) = walk.current()? {
+            let next = self.read_fat_entry(cluster).await?;
+            self.write_fat_entry(cluster, fat::FAT_FREE).await?;
+            walk.advance(next);
+        }
+        
+        Ok(())
+    }
+    
+    /// écrit des clusters contigus en un seul transfert
+    /// 
+    /// seuls les secteurs complets de `data` sont écrits.
+    pub async fn write_run(&mut self, first_cluster: u32, data: &[u8]) -> Result<(), Fat32Error<D::Error>> {
+        let (first_sector, whole) = steps::write_run_transfer(&self.geometry, first_cluster, data.len())?;
+        self.mark_dirty().await?;
+        if whole == 0 {
+            return Ok(());
+        }
+        
+        self.write_sectors(first_sector, &data[..whole]).await
+    }
+    
+    /// écrit dans un cluster, voir `Fat32Parser::write_cluster`
+    pub async fn write_cluster(&mut self, cluster: u32, data: &[u8]) -> Result<(), Fat32Error<D::Error>> {
+        let cluster_size = self.boot_sector.cluster_size() as usize;
+        self.write_run(cluster, &data[..data.len().min(cluster_size)]).await
+    }
+    
+    /// écrit le début d'un cluster et complète le reste par des zéros
+    async fn write_cluster_head(&mut self, cluster: u32, data: &[u8]) -> Result<(), Fat32Error<D::Error>> {
+        let parts = ClusterHead::write(&self.geometry, cluster, data.len())?;
+        self.mark_dirty().await?;
+        
+        for part in parts {
+            match part {
+                HeadPart::Sectors { sector, range } => self.write_sectors(sector, &data[range]).await?,
+                HeadPart::Partial { sector, range } => {
+                    let mut padded = [0u8; 512];
+                    padded[..range.len()].copy_from_slice(&data[range]);
+                    self.write_sectors(sector, &padded).await?;
+                }
+            }
+        }
+        Ok(())
+    }
+    
+    /// écrit un fichier, en prolongeant sa chaîne si nécessaire
+    pub async fn write_file(&mut self, start_cluster: u32, data: &[u8]) -> Result<(), Fat32Error<D::Error>> {
+        let mut write = FileWrite::new(start_cluster, data.len(), self.boot_sector.cluster_size());
+        let mut answer = None;
+        
+        loop {
+            match write.step(answer.take()) {
+                WriteStep::Cluster { cluster, range } => self.write_cluster(cluster, &data[range]).await?,
+                WriteStep::Head { cluster, range } => self.write_cluster_head(cluster, &data[range]).await?,
+                WriteStep::Fat(cluster) => answer = Some(self.read_fat_entry(cluster).await?),
+                WriteStep::Allocate(previous) => answer = Some(self.allocate_cluster(Some(previous)).await?),
+                WriteStep::Done => return Ok(()),
+            }
+        }
+    }
+    
+    /// écrit une entrée à une position donnée
+    pub async fn write_dir_entry(&mut self, location: EntryLocation, entry: &DirEntry) -> Result<(), Fat32Error<D::Error>> {
+        self.mark_dirty().await?;
+        
+        let mut buffer = [0u8; 512];
+        self.read_sector(location.sector, &mut buffer).await?;
+        steps::set_entry_at(&mut buffer, location.index, entry)?;
+        self.write_sector_kind(location.sector, &buffer, SectorKind::Directory).await
+    }
+    
+    async fn write_sectors(&mut self, start: u32, buffer: &[u8]) -> Result<(), Fat32Error<D::Error>> {
+        self.device.write_sectors(self.partition_start + start as u64, buffer).await
+    }
+    
+    async fn write_sector_kind(&mut self, sector: u32, buffer: &[u8], kind: SectorKind) -> Result<(), Fat32Error<D::Error>> {
+        self.device.write_sector_kind(self.partition_start + sector as u64, buffer, kind).await
+    }
+    
+    /// marque le volume « sale » avant sa première modification
+    async fn mark_dirty(&mut self) -> Result<(), Fat32Error<D::Error>> {
+        let Some(patch) = steps::dirty_patch(self.dirty) else {
+            return Ok(());
+        };
+        self.update_fat1(patch).await?;
+        self.device.flush().await?;
+        self.dirty = true;
+        Ok(())
+    }
+    
+    /// modifie l'entrée FAT 1 dans chaque copie de la FAT
+    async fn update_fat1<F>(&mut self, f: F) -> Result<(), Fat32Error<D::Error>>
+    where
+        F: Fn(u32) -> u32,
+    {
+        let mut buffer = [0u8; 512];
+        
+        for sector in steps::fat1_sectors(self.geometry) {
+            self.read_sector(sector, &mut buffer).await?;
+            steps::patch_fat1(&mut buffer, &f);
+            self.write_sector_kind(sector, &buffer, SectorKind::Fat).await?;
+        }
+        
+        Ok(())
+    }
+}
@@ -0,0 +1,202 @@
+//! bitmap des clusters libres
+//! 
+//! garde en mémoire un bit par cluster (1 = libre) pour éviter de
+//! relire la FAT à chaque allocation. le stockage est fourni par
+//! l'appelant, typiquement un buffer statique sur cible embarquée ; le
+//! parser ne peut pas survivre à ce buffer. avec la feature `alloc`, le
+//! parser alloue lui-même le bitmap à la première allocation de
+//! cluster si aucun n'est attaché. une fois attaché au parser, le bitmap
+//! est tenu à jour par `write_fat_entry`.
+
+#[cfg(feature = "alloc")]
+use alloc::vec::Vec;
+use core::ops::{Deref, DerefMut};
+use crate::operations::parser::Fat32Parser;
+use crate::traits::block_device::BlockDevice;
+use crate::utils::error::Fat32Error;
+use crate::utils::fat;
+
+/// stockage d'un bitmap
+enum Bits<'a> {
+    /// buffer fourni par l'appelant
+    Borrowed(&'a mut [u8]),
+    /// buffer alloué par le parser
+    #[cfg(feature = "alloc")]
+    Owned(Vec<u8>),
+}
+
+impl Deref for Bits<'_> {
+    type Target = [u8];
+    
+    fn deref(&self) -> &[u8] {
+        match self {
+            Bits::Borrowed(bits) => bits,
+            #[cfg(feature = "alloc")]
+            Bits::Owned(bits) => bits,
+        }
+    }
+}
+
+impl DerefMut for Bits<'_> {
+    fn deref_mut(&mut self) -> &mut [u8] {
+        match self {
+            Bits::Borrowed(bits) => bits,
+            #[cfg(feature = "alloc")]
+            Bits::Owned(bits) => bits,
+        }
+    }
+}
+
+/// bitmap des clusters libres
+pub struct FreeClusterBitmap<'a> {
+    bits: Bits<'a>,
+    max_cluster: u32,
+    free_count: u32,
+}
+
+impl FreeClusterBitmap<'_> {
+    /// taille de stockage nécessaire pour un volume
+    pub fn required_bytes(max_cluster: u32) -> usize {
+        (max_cluster as usize + 1).div_ceil(8)
+    }
+    
+    /// vérifie si un cluster est libre
+    pub fn is_free(&self, cluster: u32) -> bool {
+        if cluster < 2 || cluster > self.max_cluster {
+            return false;
+        }
+        self.bits[cluster as usize / 8] & (1 << (cluster % 8)) != 0
+    }
+    
+    /// marque un cluster libre ou occupé
+    pub fn set_free(&mut self, cluster: u32, free: bool) {
+        if cluster < 2 || cluster > self.max_cluster || self.is_free(cluster) == free {
+            return;
+        }
+        
+        let mask = 1 << (cluster % 8);
+        if free {
+            self.bits[cluster as usize / 8] |= mask;
+            self.free_count += 1;
+        } else {
+            self.bits[cluster as usize / 8] &= !mask;
+            self.free_count -= 1;
+        }
+    }
+    
+    /// retourne le nombre de clusters libres
+    pub fn free_count(&self) -> u32 {
+        self.free_count
+    }
+    
+    /// retourne le plus grand cluster suivi
+    pub fn max_cluster(&self) -> u32 {
+        self.max_cluster
+    }
+    
+    /// trouve le premier cluster libre à partir de `start`
+    /// 
+    /// les octets sans bit libre sont sautés d'un coup.
+    pub fn find_free_from(&self, start: u32) -> Option<u32> {
+        let start = start.max(2);
+        if start > self.max_cluster {
+            return None;
+        }
+        
+        let last_byte = self.max_cluster as usize / 8;
+        let mut byte = start as usize / 8;
+        let mut bits = self.bits[byte] & (0xFFu8 << (start % 8));
+        
+        loop {
+            if bits != 0 {
+                let cluster = (byte * 8) as u32 + bits.trailing_zeros();
+                return if cluster <= self.max_cluster { Some(cluster) } else { None };
+            }
+            byte += 1;
+            if byte > last_byte {
+                return None;
+            }
+            bits = self.bits[byte];
+        }
+    }
+}
+
+impl<'a, D: BlockDevice> Fat32Parser<'a, D> {
+    /// attache un bitmap des clusters libres
+    /// 
+    /// le bitmap est construit immédiatement en parcourant la FAT une fois.
+    /// retourne `BufferTooSmall` si `storage` ne couvre pas tous les
+    /// clusters (voir `FreeClusterBitmap::required_bytes`).
+    pub fn attach_free_bitmap(&mut self, storage: &'a mut [u8]) -> Result<(), Fat32Error<D::Error>> {
+        if storage.len() < FreeClusterBitmap::required_bytes(self.max_cluster()) {
+            return Err(Fat32Error::BufferTooSmall);
+        }
+        self.build_free_bitmap(Bits::Borrowed(storage))
+    }
+    
+    /// alloue et attache un bitmap des clusters libres (feature `alloc`)
+    /// 
+    /// sans effet si un bitmap est déjà attaché. appelé à la première
+    /// allocation de cluster.
+    #[cfg(feature = "alloc")]
+    pub fn enable_free_bitmap(&mut self) -> Result<(), Fat32Error<D::Error>> {
+        if self.free_bitmap.is_some() {
+            return Ok(());
+        }
+        let bytes = FreeClusterBitmap::required_bytes(self.max_cluster());
+        self.build_free_bitmap(Bits::Owned(alloc::vec![0; bytes]))
+    }
+    
+    /// remplit le bitmap en parcourant la FAT, puis l'attache
+    fn build_free_bitmap(&mut self, mut bits: Bits<'a>) -> Result<(), Fat32Error<D::Error>> {
+        bits.fill(0);
+        let mut bitmap = FreeClusterBitmap {
+            bits,
+            max_cluster: self.max_cluster(),
+            free_count: 0,
+        };
+        
+        self.scan_fat(|cluster, entry| {
+            if fat::is_free(entry) {
+                bitmap.set_free(cluster, true);
+            }
+        })?;
+        
+        self.free_bitmap = Some(bitmap);
+        Ok(())
+    }
+    
+    /// détache le bitmap et rend son stockage
+    /// 
+    /// un bitmap alloué par le parser est libéré, et `None` est retourné.
+    pub fn detach_free_bitmap(&mut self) -> Option<&'a mut [u8]> {
+        match self.free_bitmap.take()?.bits {
+            Bits::Borrowed(bits) => Some(bits),
+            #[cfg(feature = "alloc")]
+            Bits::Owned(_) => None,
+        }
+    }
+    
+    /// retourne le bitmap des clusters libres s'il est attaché
+    pub fn free_bitmap(&self) -> Option<&FreeClusterBitmap<'a>> {
+        self.free_bitmap.as_ref()
+    }
+    
+    /// reconstruit le bitmap après un changement de géométrie
+    /// 
+    /// un stockage fourni devenu trop petit est abandonné, et les
+    /// recherches repassent par la FAT ; un bitmap alloué par le parser
+    /// est réalloué à la nouvelle taille.
+    pub(crate) fn rebuild_free_bitmap(&mut self) -> Result<(), Fat32Error<D::Error>> {
+        let bytes = FreeClusterBitmap::required_bytes(self.max_cluster());
+        match self.free_bitmap.take().map(|bitmap| bitmap.bits) {
+            Some(Bits::Borrowed(storage)) if storage.len() >= bytes => self.build_free_bitmap(Bits::Borrowed(storage)),
+            #[cfg(feature = "alloc")]
+            Some(Bits::Owned(mut storage)) => {
+                storage.resize(bytes, 0);
+                self.build_free_bitmap(Bits::Owned(storage))
+            }
+            _ => Ok(()),
+        }
+    }
+}
@@ -0,0 +1,312 @@
+//! récupération de fichiers par signature (carving)
+//! 
+//! parcourt les clusters marqués libres dans la FAT et cherche, au début
+//! de chaque cluster, la signature d'un format connu. l'étendue du
+//! fichier est estimée à partir de son pied (footer) ou d'une longueur
+//! contenue dans son en-tête, en supposant une allocation contiguë.
+//! aucune écriture n'est faite sur le dispositif.
+
+use crate::operations::directory::Visit;
+use crate::operations::parser::Fat32Parser;
+use crate::traits::block_device::BlockDevice;
+use crate::utils::error::Fat32Error;
+use crate::utils::fat;
+
+/// format de fichier reconnu
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FileKind {
+    Jpeg,
+    Png,
+    Gif,
+    Pdf,
+    Zip,
+    Elf,
+    Bmp,
+}
+
+impl FileKind {
+    /// extension usuelle du format
+    pub fn extension(&self) -> &'static str {
+        match self {
+            FileKind::Jpeg => "jpg",
+            FileKind::Png => "png",
+            FileKind::Gif => "gif",
+            FileKind::Pdf => "pdf",
+            FileKind::Zip => "zip",
+            FileKind::Elf => "elf",
+            FileKind::Bmp => "bmp",
+        }
+    }
+}
+
+/// signature d'un format
+struct Signature {
+    kind: FileKind,
+    header: &'static [u8],
+    /// pied marquant la fin du fichier
+    footer: Option<&'static [u8]>,
+    /// octets qui suivent le pied
+    footer_extra: u32,
+}
+
+const SIGNATURES: &[Signature] = &[
+    Signature { kind: FileKind::Jpeg, header: &[0xFF, 0xD8, 0xFF], footer: Some(&[0xFF, 0xD9]), footer_extra: 0 },
+    Signature {
+        kind: FileKind::Png,
+        header: &[0x89, b'P', b'N', b'G', 0x0D, 0x0A, 0x1A, 0x0A],
+        footer: Some(&[b'I', b'E', b'N', b'D', 0xAE, 0x42, 0x60, 0x82]),
+        footer_extra: 0,
+    },
+    Signature { kind: FileKind::Gif, header: b"GIF87a", footer: Some(&[0x00, 0x3B]), footer_extra: 0 },
+    Signature { kind: FileKind::Gif, header: b"GIF89a", footer: Some(&[0x00, 0x3B]), footer_extra: 0 },
+    Signature { kind: FileKind::Pdf, header: b"%PDF-", footer: Some(b"%%EOF"), footer_extra: 0 },
+    // fin du répertoire central, sans le commentaire éventuel
+    Signature { kind: FileKind::Zip, header: b"PK\x03\x04", footer: Some(b"PK\x05\x06"), footer_extra: 18 },
+    Signature { kind: FileKind::Elf, header: b"\x7FELF", footer: None, footer_extra: 0 },
+    Signature { kind: FileKind::Bmp, header: b"BM", footer: None, footer_extra: 0 },
+];
+
+/// longueur maximale d'un pied
+const MAX_FOOTER_LEN: usize = 8;
+
+/// options de recherche
+#[derive(Debug, Clone, Copy)]
+pub struct CarvingOptions {
+    /// taille maximale d'un fichier candidat en octets
+    pub max_file_size: u32,
+}
+
+impl Default for CarvingOptions {
+    fn default() -> Self {
+        Self { max_file_size: 16 * 1024 * 1024 }
+    }
+}
+
+/// fichier candidat retrouvé dans les clusters libres
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CarvedFile {
+    /// format reconnu
+    pub kind: FileKind,
+    /// cluster où commence le fichier
+    pub start_cluster: u32,
+    /// nombre de clusters couverts
+    pub cluster_count: u32,
+    /// taille estimée en octets
+    pub length: u32,
+    /// `true` si la taille vient du pied ou de l'en-tête, `false` si elle
+    /// se limite aux clusters libres consécutifs
+    pub length_known: bool,
+}
+
+impl<D: BlockDevice> Fat32Parser<'_, D> {
+    /// cherche des fichiers dans les clusters libres
+    /// 
+    /// sur une image tronquée, seuls les clusters présents sur le
+    /// dispositif sont examinés.
+    pub fn carve<F>(&self, options: CarvingOptions, mut f: F) -> Result<(), Fat32Error<D::Error>>
+    where
+        F: FnMut(&CarvedFile) -> Result<Visit, Fat32Error<D::Error>>,
+    {
+        let max_cluster = self.readable_max_cluster();
+        let mut buffer = [0u8; 512];
+        let mut cluster = 2;
+        
+        while cluster <= max_cluster {
+            if !fat::is_free(self.read_fat_entry(cluster)?) {
+                cluster += 1;
+                continue;
+            }
+            
+            self.read_sector(self.cluster_sector(cluster)?, &mut buffer)?;
+            let signature = SIGNATURES.iter().find(|s| {
+                buffer.starts_with(s.header) && header_is_plausible(s.kind, &buffer)
+            });
+            
+            let candidate = match signature {
+                Some(signature) => self.estimate_extent(signature, cluster, &buffer, options)?,
+                None => None,
+            };
+            
+            match candidate {
+                Some(carved) => {
+                    if f(&carved)? == Visit::Stop {
+                        return Ok(());
+                    }
+                    // une taille inconnue peut englober d'autres fichiers
+                    cluster += if carved.length_known { carved.cluster_count.max(1) } else { 1 };
+                }
+                None => cluster += 1,
+            }
+        }
+        
+        Ok(())
+    }
+    
+    /// lit le contenu d'un fichier candidat à partir de `offset`
+    /// 
+    /// retourne le nombre d'octets copiés dans `buffer`.
+    pub fn read_carved(&self, carved: &CarvedFile, offset: u32, buffer: &mut [u8]) -> Result<usize, Fat32Error<D::Error>> {
+        if offset >= carved.length {
+            return Ok(0);
+        }
+        
+        let first_sector = self.cluster_sector(carved.start_cluster)?;
+        let wanted = core::cmp::min(buffer.len(), (carved.length - offset) as usize);
+        let mut sector_buffer = [0u8; 512];
+        let mut copied = 0;
+        
+        while copied < wanted {
+            let position = offset as usize + copied;
+            let in_sector = position % 512;
+            self.read_sector(first_sector + (position / 512) as u32, &mut sector_buffer)?;
+            
+            let chunk = core::cmp::min(512 - in_sector, wanted - copied);
+            buffer[copied..copied + chunk].copy_from_slice(&sector_buffer[in_sector..in_sector + chunk]);
+            copied += chunk;
+        }
+        
+        Ok(copied)
+    }
+    
+    /// nombre de clusters libres consécutifs à partir de `start`, borné
+    fn free_run_length(&self, start: u32, max_clusters: u32) -> Result<u32, Fat32Error<D::Error>> {
+        let max_cluster = self.readable_max_cluster();
+        let mut length = 0;
+        
+        while length < max_clusters
+            && start + length <= max_cluster
+            && fat::is_free(self.read_fat_entry(start + length)?)
+        {
+            length += 1;
+        }
+        
+        Ok(length)
+    }
+    
+    fn estimate_extent(
+        &self,
+        signature: &Signature,
+        start: u32,
+        first_sector: &[u8; 512],
+        options: CarvingOptions,
+    ) -> Result<Option<CarvedFile>, Fat32Error<D::Error>> {
+        let cluster_size = self.boot_sector.cluster_size();
+        let max_clusters = options.max_file_size.div_ceil(cluster_size).max(1);
+        
+        let embedded = embedded_length(signature.kind, first_sector);
+        if embedded.is_some_and(|length| length == 0 || length > options.max_file_size) {
+            return Ok(None);
+        }
+        
+        let run = self.free_run_length(start, max_clusters)?;
+        let run_bytes = run.saturating_mul(cluster_size).min(options.max_file_size);
+        
+        // taille contenue dans l'en-tête : la lecture suppose des clusters
+        // contigus, elle s'arrête donc à la fin des clusters libres
+        if let Some(length) = embedded {
+            return Ok(Some(CarvedFile {
+                kind: signature.kind,
+                start_cluster: start,
+                cluster_count: length.div_ceil(cluster_size).min(run),
+                length: length.min(run_bytes),
+                length_known: length <= run_bytes,
+            }));
+        }
+        
+        let length = match signature.footer {
+            Some(footer) => self.find_footer(start, signature.header.len() as u32, run_bytes, footer)?
+                .map(|end| end.saturating_add(signature.footer_extra).min(run_bytes)),
+            None => None,
+        };
+        
+        Ok(Some(CarvedFile {
+            kind: signature.kind,
+            start_cluster: start,
+            cluster_count: length.unwrap_or(run_bytes).div_ceil(cluster_size),
+            length: length.unwrap_or(run_bytes),
+            length_known: length.is_some(),
+        }))
+    }
+    
+    /// cherche un pied dans les `limit` premiers octets à partir de `start`
+    /// 
+    /// retourne la position qui suit le pied. les secteurs sont lus un par
+    /// un en gardant la fin du précédent pour trouver un pied à cheval ;
+    /// un dernier secteur incomplet est lu, mais la recherche s'arrête à
+    /// `limit`.
+    fn find_footer(&self, start: u32, skip: u32, limit: u32, footer: &[u8]) -> Result<Option<u32>, Fat32Error<D::Error>> {
+        let first_sector = self.cluster_sector(start)?;
+        let sectors = limit.div_ceil(512);
+        let keep = footer.len() - 1;
+        
+        let mut window = [0u8; 512 + MAX_FOOTER_LEN];
+        let mut carried = 0;
+        
+        for s in 0..sectors {
+            self.read_sector(first_sector + s, &mut window[carried..carried + 512])?;
+            let filled = carried + 512;
+            let window_start = s * 512 - carried as u32;
+            let valid = filled.min((limit - window_start) as usize);
+            
+            let found = window[..valid].windows(footer.len())
+                .enumerate()
+                .find(|&(i, w)| w == footer && window_start + i as u32 >= skip);
+            if let Some((i, _)) = found {
+                return Ok(Some(window_start + (i + footer.len()) as u32));
+            }
+            
+            window.copy_within(filled - keep..filled, 0);
+            carried = keep;
+        }
+        
+        Ok(None)
+    }
+}
+
+/// écarte les signatures trop courtes pour être fiables seules
+fn header_is_plausible(kind: FileKind, data: &[u8; 512]) -> bool {
+    match kind {
+        // champs réservés à zéro et en-tête DIB de taille connue
+        FileKind::Bmp => {
+            data[6..10] == [0, 0, 0, 0]
+                && matches!(u32::from_le_bytes([data[14], data[15], data[16], data[17]]), 12 | 40 | 52 | 56 | 108 | 124)
+        }
+        // classe 32 ou 64 bits, little ou big endian
+        FileKind::Elf => matches!(data[4], 1 | 2) && matches!(data[5], 1 | 2),
+        _ => true,
+    }
+}
+
+/// taille du fichier contenue dans l'en-tête
+fn embedded_length(kind: FileKind, data: &[u8; 512]) -> Option<u32> {
+    match kind {
+        FileKind::Bmp => Some(u32::from_le_bytes([data[2], data[3], data[4], data[5]])),
+        FileKind::Elf => elf_length(data),
+        _ => None,
+    }
+}
+
+/// fin de la table des sections d'un ELF, qui termine normalement le fichier
+fn elf_length(data: &[u8; 512]) -> Option<u32> {
+    let big_endian = data[5] == 2;
+    let u16_at = |o: usize| {
+        let bytes = [data[o], data[o + 1]];
+        if big_endian { u16::from_be_bytes(bytes) } else { u16::from_le_bytes(bytes) }
+    };
+    let u32_at = |o: usize| {
+        let bytes = [data[o], data[o + 1], data[o + 2], data[o + 3]];
+        if big_endian { u32::from_be_bytes(bytes) } else { u32::from_le_bytes(bytes) }
+    };
+    
+    let (section_offset, entry_size, count) = if data[4] == 1 {
+        (u32_at(0x20) as u64, u16_at(0x2E), u16_at(0x30))
+    } else {
+        let first = u32_at(0x28) as u64;
+        let second = u32_at(0x2C) as u64;
+        let offset = if big_endian { (first << 32) | second } else { (second << 32) | first };
+        (offset, u16_at(0x3A), u16_at(0x3C))
+    };
+    
+    let end = section_offset.checked_add(entry_size as u64 * count as u64)?;
+    u32::try_from(end).ok()
+}
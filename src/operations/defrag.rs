@@ -0,0 +1,269 @@
+//! défragmentation
+//! 
+//! rend contiguës les chaînes de clusters des fichiers et répertoires,
+//! et peut regrouper les données en début de volume pour laisser tout
+//! l'espace libre à la fin. chaque déplacement laisse le système de
+//! fichiers cohérent, l'opération peut donc être interrompue entre deux
+//! fichiers.
+
+use crate::operations::directory::{EntryLocation, ENTRIES_PER_SECTOR};
+use crate::operations::parser::Fat32Parser;
+use crate::operations::relocate::{ClusterOwner, ClusterReferences};
+use crate::structures::dir_entry::DirEntry;
+use crate::traits::block_device::{BlockDevice, BlockDeviceMut};
+use crate::utils::constants::{ENTRY_DELETED, ENTRY_EMPTY};
+use crate::utils::error::Fat32Error;
+use crate::utils::fat;
+
+/// options de défragmentation
+#[derive(Debug, Clone, Copy, Default)]
+pub struct DefragOptions {
+    /// supprime les emplacements libérés dans les répertoires
+    pub compact_directories: bool,
+    /// regroupe les données en début de volume
+    pub consolidate_free_space: bool,
+}
+
+/// bilan d'une défragmentation
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct DefragReport {
+    /// fichiers et répertoires examinés
+    pub files_visited: u32,
+    /// fichiers et répertoires déplacés
+    pub files_moved: u32,
+    /// clusters déplacés
+    pub clusters_moved: u32,
+    /// fichiers laissés en place faute d'espace contigu
+    pub files_skipped: u32,
+    /// répertoires compactés
+    pub directories_compacted: u32,
+    /// la défragmentation a été interrompue par le callback
+    pub interrupted: bool,
+}
+
+impl<D: BlockDevice> Fat32Parser<'_, D> {
+    /// trouve une suite de `length` clusters libres consécutifs
+    pub fn find_free_run(&self, length: u32) -> Result<Option<u32>, Fat32Error<D::Error>> {
+        self.find_free_run_between(2, self.max_cluster(), length)
+    }
+    
+    /// vérifie qu'une chaîne occupe des clusters consécutifs
+    fn is_contiguous(&self, head: u32) -> Result<bool, Fat32Error<D::Error>> {
+        let mut current = head;
+        loop {
+            let next = self.read_fat_entry(current)?;
+            if fat::is_eoc(next) {
+                return Ok(true);
+            }
+            if next != current + 1 {
+                return Ok(false);
+            }
+            current = next;
+        }
+    }
+    
+    /// choisit où placer une chaîne lors du regroupement
+    /// 
+    /// la plage commence au curseur et saute les clusters défectueux.
+    fn consolidation_target(&self, cursor: u32, length: u32) -> Result<Option<u32>, Fat32Error<D::Error>> {
+        let max_cluster = self.max_cluster();
+        let mut start = cursor;
+        
+        while start + length - 1 <= max_cluster {
+            match self.find_bad_cluster(start, length)? {
+                Some(bad) => start = bad + 1,
+                None => return Ok(Some(start)),
+            }
+        }
+        
+        Ok(None)
+    }
+    
+    /// retourne le premier cluster défectueux de la plage `start..start + length`
+    fn find_bad_cluster(&self, start: u32, length: u32) -> Result<Option<u32>, Fat32Error<D::Error>> {
+        for cluster in start..start + length {
+            if fat::is_bad(self.read_fat_entry(cluster)?) {
+                return Ok(Some(cluster));
+            }
+        }
+        Ok(None)
+    }
+    
+    /// trouve un cluster libre hors de la plage `start..start + length`
+    fn find_free_outside(&self, start: u32, length: u32) -> Result<Option<u32>, Fat32Error<D::Error>> {
+        for cluster in (start + length..=self.max_cluster()).chain(2..start) {
+            if fat::is_free(self.read_fat_entry(cluster)?) {
+                return Ok(Some(cluster));
+            }
+        }
+        Ok(None)
+    }
+    
+    /// retourne la position du n-ième emplacement d'un répertoire
+    fn dir_slot_location(&self, dir_cluster: u32, slot: u32) -> Result<EntryLocation, Fat32Error<D::Error>> {
+        let sectors_per_cluster = self.boot_sector.sectors_per_cluster as u32;
+        let slots_per_cluster = sectors_per_cluster * ENTRIES_PER_SECTOR as u32;
+        
+        let cluster = self.chain_cluster_at(dir_cluster, slot / slots_per_cluster)?;
+        let in_cluster = slot % slots_per_cluster;
+        
+        Ok(EntryLocation {
+            cluster,
+            sector: self.cluster_sector(cluster)? + in_cluster / ENTRIES_PER_SECTOR as u32,
+            index: (in_cluster % ENTRIES_PER_SECTOR as u32) as usize,
+        })
+    }
+}
+
+impl<D: BlockDeviceMut> Fat32Parser<'_, D> {
+    /// défragmente le volume
+    /// 
+    /// `should_continue` est appelé avant chaque fichier ; retourner
+    /// `false` arrête proprement la défragmentation.
+    pub fn defragment<F>(&mut self, options: DefragOptions, mut should_continue: F) -> Result<DefragReport, Fat32Error<D::Error>>
+    where
+        F: FnMut(&DefragReport) -> bool,
+    {
+        let mut report = DefragReport::default();
+        let mut references = self.cluster_references()?;
+        let mut cursor = 2;
+        let mut index = 0;
+        
+        while let Some(chain) = self.chain_at(&references, index)? {
+            if !should_continue(&report) {
+                report.interrupted = true;
+                break;
+            }
+            
+            index += 1;
+            report.files_visited += 1;
+            
+            let head = chain.head;
+            if chain.is_directory && options.compact_directories && self.compact_directory(head)? {
+                self.refresh_references(head, &mut references)?;
+                report.directories_compacted += 1;
+            }
+            
+            let length = self.chain_length(head)?;
+            
+            let start = if options.consolidate_free_space {
+                match self.consolidation_target(cursor, length)? {
+                    Some(start) => start,
+                    None => {
+                        report.files_skipped += 1;
+                        continue;
+                    }
+                }
+            } else if self.is_contiguous(head)? {
+                continue;
+            } else {
+                match self.find_free_run(length)? {
+                    Some(start) => start,
+                    None => {
+                        report.files_skipped += 1;
+                        continue;
+                    }
+                }
+            };
+            
+            let moved = self.move_chain(head, length, start, &mut references)?;
+            if moved > 0 {
+                report.files_moved += 1;
+                report.clusters_moved += moved;
+            }
+            cursor = start + length;
+        }
+        
+        if report.directories_compacted > 0 {
+            self.invalidate_free_count()?;
+        }
+        
+        Ok(report)
+    }
+    
+    /// déplace une chaîne vers les clusters `start..start + length`
+    /// 
+    /// les clusters de la plage occupés par d'autres chaînes sont d'abord
+    /// déplacés ailleurs. la chaîne est suivie une seule fois : chaque
+    /// cluster placé devient le prédécesseur connu du suivant. retourne
+    /// le nombre de clusters déplacés.
+    fn move_chain(&mut self, head: u32, length: u32, start: u32, references: &mut ClusterReferences) -> Result<u32, Fat32Error<D::Error>> {
+        let mut current = head;
+        let mut previous = None;
+        let mut moved = 0;
+        
+        for target in start..start + length {
+            if current != target {
+                // l'éviction peut déplacer un cluster plus loin dans cette
+                // même chaîne : la FAT est relue ensuite
+                if !fat::is_free(self.read_fat_entry(target)?) {
+                    let spare = self.find_free_outside(start, length)?.ok_or(Fat32Error::DiskFull)?;
+                    self.relocate_tracked(target, spare, None, references)?;
+                    moved += 1;
+                }
+                
+                self.relocate_tracked(current, target, previous.map(ClusterOwner::Predecessor), references)?;
+                moved += 1;
+            }
+            
+            previous = Some(target);
+            current = self.read_fat_entry(target)?;
+            if fat::is_eoc(current) {
+                break;
+            }
+        }
+        
+        Ok(moved)
+    }
+    
+    /// supprime les emplacements libérés d'un répertoire
+    /// 
+    /// les entrées actives sont ramenées au début dans le même ordre, puis
+    /// les clusters devenus inutiles en fin de chaîne sont libérés.
+    /// retourne `true` si le répertoire a été modifié.
+    pub fn compact_directory(&mut self, dir_cluster: u32) -> Result<bool, Fat32Error<D::Error>> {
+        let sectors_per_cluster = self.boot_sector.sectors_per_cluster as u32;
+        let slots_per_cluster = sectors_per_cluster * ENTRIES_PER_SECTOR as u32;
+        let total_slots = self.chain_length(dir_cluster)? * slots_per_cluster;
+        
+        let mut write = 0;
+        let mut read = 0;
+        while read < total_slots {
+            let location = self.dir_slot_location(dir_cluster, read)?;
+            let entry = self.read_dir_entry(location)?;
+            if entry.name[0] == ENTRY_EMPTY {
+                break;
+            }
+            if entry.name[0] != ENTRY_DELETED {
+                if write != read {
+                    let target = self.dir_slot_location(dir_cluster, write)?;
+                    self.write_dir_entry(target, &entry)?;
+                }
+                write += 1;
+            }
+            read += 1;
+        }
+        
+        if write == read {
+            return Ok(false);
+        }
+        
+        // effacer les emplacements libérés
+        let empty = DirEntry::from_bytes(&[0u8; 32]);
+        for slot in write..read {
+            let location = self.dir_slot_location(dir_cluster, slot)?;
+            self.write_dir_entry(location, &empty)?;
+        }
+        
+        // garder au moins un cluster
+        let keep = core::cmp::max(1, write.div_ceil(slots_per_cluster));
+        let last_kept = self.chain_cluster_at(dir_cluster, keep - 1)?;
+        let next = self.read_fat_entry(last_kept)?;
+        if !fat::is_eoc(next) {
+            self.write_fat_entry(last_kept, fat::FAT_EOC)?;
+            self.free_cluster_chain(next)?;
+        }
+        
+        Ok(true)
+    }
+}
@@ -0,0 +1,194 @@
+//! parcours des répertoires
+//! 
+//! fonctions pour parcourir les entrées d'un répertoire en suivant sa
+//! chaîne de clusters, et pour modifier une entrée en place. aucune
+//! allocation : les entrées sont passées une par une à un callback.
+
+use crate::operations::parser::Fat32Parser;
+use crate::operations::steps::{self, DirStep, DirWalk};
+use crate::structures::dir_entry::DirEntry;
+use crate::traits::block_device::{BlockDevice, BlockDeviceMut, SectorKind};
+use crate::utils::constants::ENTRY_EMPTY;
+use crate::utils::error::Fat32Error;
+
+/// nombre d'entrées de 32 octets par secteur
+pub const ENTRIES_PER_SECTOR: usize = 512 / 32;
+
+/// profondeur maximale de parcours de l'arborescence
+pub const MAX_DEPTH: usize = 32;
+
+/// état d'un parcours de l'arborescence
+struct TreeWalk {
+    /// clusters des répertoires en cours de parcours, racine en tête
+    ancestors: [u32; MAX_DEPTH],
+    /// nombre de répertoires encore autorisés
+    budget: u32,
+}
+
+/// position d'une entrée de répertoire sur le disque
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EntryLocation {
+    /// cluster du répertoire contenant l'entrée
+    pub cluster: u32,
+    /// secteur contenant l'entrée
+    pub sector: u32,
+    /// index de l'entrée dans le secteur
+    pub index: usize,
+}
+
+/// suite du parcours après la visite d'une entrée
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Visit {
+    /// continuer le parcours
+    Continue,
+    /// arrêter le parcours
+    Stop,
+}
+
+impl<D: BlockDevice> Fat32Parser<'_, D> {
+    /// parcourt les entrées d'un répertoire jusqu'au marqueur de fin
+    /// 
+    /// les entrées supprimées et LFN sont aussi passées au callback.
+    pub fn walk_dir<F>(&self, dir_cluster: u32, f: &mut F) -> Result<Visit, Fat32Error<D::Error>>
+    where
+        F: FnMut(&DirEntry, EntryLocation) -> Result<Visit, Fat32Error<D::Error>>,
+    {
+        self.walk_slots(dir_cluster, true, f)
+    }
+    
+    /// parcourt tous les emplacements d'un répertoire, y compris ceux
+    /// situés après le marqueur de fin
+    pub fn walk_dir_slots<F>(&self, dir_cluster: u32, f: &mut F) -> Result<Visit, Fat32Error<D::Error>>
+    where
+        F: FnMut(&DirEntry, EntryLocation) -> Result<Visit, Fat32Error<D::Error>>,
+    {
+        self.walk_slots(dir_cluster, false, f)
+    }
+    
+    /// parcourt récursivement l'arborescence depuis la racine
+    /// 
+    /// seules les entrées actives (ni supprimées, ni LFN, ni "." / "..")
+    /// sont passées au callback, avec leur profondeur. les répertoires
+    /// coupés par la fin d'une image tronquée sont passés au callback
+    /// mais pas parcourus.
+    pub fn walk_tree<F>(&self, f: &mut F) -> Result<Visit, Fat32Error<D::Error>>
+    where
+        F: FnMut(&DirEntry, EntryLocation, usize) -> Result<Visit, Fat32Error<D::Error>>,
+    {
+        let mut walk = TreeWalk {
+            ancestors: [0; MAX_DEPTH],
+            budget: self.max_cluster(),
+        };
+        self.walk_tree_from(self.boot_sector.root_cluster, 0, &mut walk, f)
+    }
+    
+    fn walk_tree_from<F>(
+        &self,
+        dir_cluster: u32,
+        depth: usize,
+        walk: &mut TreeWalk,
+        f: &mut F,
+    ) -> Result<Visit, Fat32Error<D::Error>>
+    where
+        F: FnMut(&DirEntry, EntryLocation, usize) -> Result<Visit, Fat32Error<D::Error>>,
+    {
+        // un répertoire qui contient l'un de ses ancêtres boucle, et chaque
+        // répertoire occupe au moins un cluster : au-delà, le disque ment
+        if depth >= MAX_DEPTH || walk.ancestors[..depth].contains(&dir_cluster) || walk.budget == 0 {
+            return Err(Fat32Error::InvalidCluster { cluster: dir_cluster });
+        }
+        walk.ancestors[depth] = dir_cluster;
+        walk.budget -= 1;
+        
+        self.walk_dir(dir_cluster, &mut |entry, location| {
+            if entry.is_empty() || entry.is_long_name() || entry.is_dot() || entry.is_dotdot() {
+                return Ok(Visit::Continue);
+            }
+            
+            if f(entry, location, depth)? == Visit::Stop {
+                return Ok(Visit::Stop);
+            }
+            
+            // sur une image tronquée, un répertoire coupé n'est pas
+            // parcouru ; `for_each_truncated` le signale
+            let cluster = entry.first_cluster();
+            if entry.is_directory() && cluster >= 2 && self.first_missing_cluster(cluster, u32::MAX)?.is_none() {
+                return self.walk_tree_from(cluster, depth + 1, walk, f);
+            }
+            
+            Ok(Visit::Continue)
+        })
+    }
+    
+    fn walk_slots<F>(&self, dir_cluster: u32, stop_at_end: bool, f: &mut F) -> Result<Visit, Fat32Error<D::Error>>
+    where
+        F: FnMut(&DirEntry, EntryLocation) -> Result<Visit, Fat32Error<D::Error>>,
+    {
+        let mut walk = DirWalk::new(dir_cluster, &self.geometry);
+        let mut buffer = [0u8; 512];
+        let mut entry = None;
+        
+        loop {
+            match walk.step(entry.take())? {
+                DirStep::Sector { cluster, sector } => {
+                    self.read_sector(sector, &mut buffer)?;
+                    if let Some(visit) = visit_sector(&buffer, cluster, sector, stop_at_end, f)? {
+                        return Ok(visit);
+                    }
+                }
+                DirStep::Fat(cluster) => entry = Some(self.read_fat_entry(cluster)?),
+                DirStep::Done => return Ok(Visit::Continue),
+            }
+        }
+    }
+    
+    /// lit l'entrée située à une position donnée
+    pub fn read_dir_entry(&self, location: EntryLocation) -> Result<DirEntry, Fat32Error<D::Error>> {
+        let mut buffer = [0u8; 512];
+        self.read_sector(location.sector, &mut buffer)?;
+        steps::entry_at(&buffer, location.index)
+    }
+}
+
+impl<D: BlockDeviceMut> Fat32Parser<'_, D> {
+    /// écrit une entrée à une position donnée
+    pub fn write_dir_entry(&mut self, location: EntryLocation, entry: &DirEntry) -> Result<(), Fat32Error<D::Error>> {
+        self.mark_dirty()?;
+        
+        let mut buffer = [0u8; 512];
+        self.read_sector(location.sector, &mut buffer)?;
+        steps::set_entry_at(&mut buffer, location.index, entry)?;
+        self.write_sector_kind(location.sector, &buffer, SectorKind::Directory)
+    }
+}
+
+/// passe au callback les entrées d'un secteur de répertoire
+/// 
+/// retourne `Some` si le parcours doit s'arrêter, soit parce que le
+/// callback l'a demandé, soit parce que le marqueur de fin a été atteint.
+pub(crate) fn visit_sector<E, F>(
+    buffer: &[u8; 512],
+    cluster: u32,
+    sector: u32,
+    stop_at_end: bool,
+    f: &mut F,
+) -> Result<Option<Visit>, Fat32Error<E>>
+where
+    F: FnMut(&DirEntry, EntryLocation) -> Result<Visit, Fat32Error<E>>,
+{
+    for index in 0..ENTRIES_PER_SECTOR {
+        let offset = index * 32;
+        let entry = DirEntry::parse(&buffer[offset..offset + 32]).map_err(Fat32Error::widen)?;
+        
+        if stop_at_end && entry.name[0] == ENTRY_EMPTY {
+            return Ok(Some(Visit::Continue));
+        }
+        
+        let location = EntryLocation { cluster, sector, index };
+        if f(&entry, location)? == Visit::Stop {
+            return Ok(Some(Visit::Stop));
+        }
+    }
+    
+    Ok(None)
+}
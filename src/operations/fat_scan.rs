@@ -0,0 +1,110 @@
+//! parcours de la FAT par lots
+//! 
+//! lit la table par groupes de secteurs et décode toutes les entrées de
+//! chaque secteur, au lieu d'un `read_fat_entry` (donc d'une lecture de
+//! secteur) par cluster. sert aux statistiques d'occupation.
+
+use crate::operations::parser::Fat32Parser;
+use crate::structures::geometry::FAT_ENTRIES_PER_SECTOR;
+use crate::traits::block_device::BlockDevice;
+use crate::utils::error::Fat32Error;
+use crate::utils::fat;
+
+/// nombre de secteurs de FAT lus par lot
+pub const FAT_SCAN_BATCH: usize = 8;
+
+/// résumé de l'occupation du volume
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct FatUsage {
+    /// nombre de clusters de données
+    pub total_clusters: u32,
+    /// clusters libres
+    pub free_clusters: u32,
+    /// clusters appartenant à une chaîne
+    pub used_clusters: u32,
+    /// clusters marqués défectueux
+    pub bad_clusters: u32,
+    /// fins de chaîne, soit le nombre de fichiers et répertoires non vides
+    pub chains: u32,
+    /// liens vers un cluster autre que le suivant
+    pub fragmented_links: u32,
+    /// plus longue suite de clusters libres consécutifs
+    pub largest_free_run: u32,
+}
+
+impl FatUsage {
+    /// vérifie si toutes les chaînes sont contiguës
+    pub fn is_unfragmented(&self) -> bool {
+        self.fragmented_links == 0
+    }
+}
+
+impl<D: BlockDevice> Fat32Parser<'_, D> {
+    /// appelle `f(cluster, valeur)` pour chaque cluster de données
+    /// 
+    /// la valeur est masquée sur 28 bits. les secteurs sont lus par lots
+    /// de `FAT_SCAN_BATCH`.
+    pub fn scan_fat<F>(&self, mut f: F) -> Result<(), Fat32Error<D::Error>>
+    where
+        F: FnMut(u32, u32),
+    {
+        let max_cluster = self.max_cluster();
+        let fat_start = self.geometry.fat_start;
+        let fat_sectors = self.geometry.fat_sectors_used();
+        let mut batch = [0u8; 512 * FAT_SCAN_BATCH];
+        
+        let mut sector = 0;
+        while sector < fat_sectors {
+            let count = core::cmp::min(FAT_SCAN_BATCH as u32, fat_sectors - sector);
+            self.read_sectors(fat_start + sector, &mut batch[..count as usize * 512])?;
+            
+            let first = sector * FAT_ENTRIES_PER_SECTOR;
+            for (i, raw) in batch[..count as usize * 512].chunks_exact(4).enumerate() {
+                let cluster = first + i as u32;
+                if cluster > max_cluster {
+                    break;
+                }
+                if cluster >= 2 {
+                    f(cluster, u32::from_le_bytes([raw[0], raw[1], raw[2], raw[3]]) & fat::FAT_MASK);
+                }
+            }
+            
+            sector += count;
+        }
+        
+        Ok(())
+    }
+    
+    /// calcule l'occupation du volume en un seul parcours de la FAT
+    pub fn fat_usage(&self) -> Result<FatUsage, Fat32Error<D::Error>> {
+        let mut usage = FatUsage {
+            total_clusters: self.max_cluster().saturating_sub(1),
+            ..FatUsage::default()
+        };
+        let mut free_run = 0;
+        
+        self.scan_fat(|cluster, value| {
+            if fat::is_free(value) {
+                usage.free_clusters += 1;
+                free_run += 1;
+                usage.largest_free_run = usage.largest_free_run.max(free_run);
+                return;
+            }
+            free_run = 0;
+            
+            if fat::is_bad(value) {
+                usage.bad_clusters += 1;
+                return;
+            }
+            
+            usage.used_clusters += 1;
+            if fat::is_eoc(value) {
+                usage.chains += 1;
+            } else if value != cluster + 1 {
+                usage.fragmented_links += 1;
+            }
+        })?;
+        
+        Ok(usage)
+    }
+}
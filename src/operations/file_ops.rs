@@ -4,6 +4,8 @@
 //! et des répertoires.
 
 use crate::structures::dir_entry::{DirEntry, ATTR_DIRECTORY, ATTR_ARCHIVE};
+use crate::structures::lfn_entry::{LfnEntry, LFN_CHARS_PER_ENTRY, LFN_MAX_ENTRIES, LFN_MAX_LEN};
+use crate::utils::error::Fat32Error;
 
 /// crée une nouvelle entrée de fichier
 /// 
@@ -97,3 +99,40 @@ pub fn names_match(name1: &[u8; 11], name2: &[u8; 11]) -> bool {
     name1 == name2
 }
 
+
+/// crée les entrées LFN d'un nom long
+/// 
+/// les entrées sont écrites dans `out` dans l'ordre du disque (dernière
+/// partie en premier). retourne le nombre d'entrées, `NameTooLong` si le
+/// nom dépasse 255 caractères UTF-16 et `InvalidName` s'il est vide.
+pub fn create_lfn_entries(
+    name: &str,
+    short_name: &[u8; 11],
+    out: &mut [LfnEntry; LFN_MAX_ENTRIES],
+) -> Result<usize, Fat32Error> {
+    let mut chars = [0u16; LFN_MAX_LEN];
+    let mut len = 0;
+    
+    for unit in name.encode_utf16() {
+        if len == LFN_MAX_LEN {
+            return Err(Fat32Error::NameTooLong { length: name.encode_utf16().count() });
+        }
+        chars[len] = unit;
+        len += 1;
+    }
+    
+    if len == 0 {
+        return Err(Fat32Error::InvalidName);
+    }
+    
+    let checksum = crate::utils::helpers::lfn_checksum(short_name);
+    let count = len.div_ceil(LFN_CHARS_PER_ENTRY);
+    
+    for sequence in 1..=count {
+        let start = (sequence - 1) * LFN_CHARS_PER_ENTRY;
+        let end = core::cmp::min(start + LFN_CHARS_PER_ENTRY, len);
+        out[count - sequence] = LfnEntry::new(sequence as u8, sequence == count, checksum, &chars[start..end]);
+    }
+    
+    Ok(count)
+}
@@ -0,0 +1,110 @@
+//! listes allouées (feature `alloc`)
+//! 
+//! raccourcis pour les environnements disposant d'un tas : les entrées
+//! d'un répertoire sont rassemblées dans un `Vec` avec leur nom complet,
+//! nom long compris, au lieu d'être passées une par une à un callback.
+
+use alloc::string::String;
+use alloc::vec::Vec;
+use crate::operations::directory::{EntryLocation, Visit};
+use crate::operations::file_info::FileInfo;
+use crate::operations::parser::Fat32Parser;
+use crate::structures::dir_entry::{DirEntry, ATTR_VOLUME_ID};
+use crate::structures::lfn_entry::{LfnEntry, LongName};
+use crate::traits::block_device::BlockDevice;
+use crate::utils::error::Fat32Error;
+use crate::utils::helpers::{lfn_checksum, short_name_string};
+
+/// entrée de répertoire avec son nom affichable
+#[derive(Debug, Clone)]
+pub struct DirItem {
+    /// nom long s'il existe, sinon nom court au format « NOM.EXT »
+    pub name: String,
+    /// attributs, taille et premier cluster
+    pub info: FileInfo,
+    /// position de l'entrée 8.3
+    pub location: EntryLocation,
+}
+
+impl<D: BlockDevice> Fat32Parser<'_, D> {
+    /// liste les fichiers et sous-répertoires d'un répertoire
+    /// 
+    /// les entrées supprimées, l'étiquette de volume et "." / ".." sont
+    /// ignorées. un nom long dont le checksum ne correspond pas à l'entrée
+    /// 8.3 est écarté au profit du nom court.
+    pub fn list_dir(&self, dir_cluster: u32) -> Result<Vec<DirItem>, Fat32Error<D::Error>> {
+        let mut items = Vec::new();
+        let mut lfn: Vec<LfnEntry> = Vec::new();
+        
+        self.walk_dir(dir_cluster, &mut |entry, location| {
+            if entry.is_empty() {
+                lfn.clear();
+                return Ok(Visit::Continue);
+            }
+            if entry.is_long_name() {
+                lfn.push(LfnEntry::from_bytes(&entry.to_bytes()));
+                return Ok(Visit::Continue);
+            }
+            
+            let parts = core::mem::take(&mut lfn);
+            if entry.attributes & ATTR_VOLUME_ID != 0 || entry.is_dot() || entry.is_dotdot() {
+                return Ok(Visit::Continue);
+            }
+            
+            items.push(DirItem {
+                name: long_name(&parts, entry).unwrap_or_else(|| short_name_string(&entry.name)),
+                info: FileInfo::from_dir_entry(entry),
+                location,
+            });
+            Ok(Visit::Continue)
+        })?;
+        
+        Ok(items)
+    }
+    
+    /// liste le sous-répertoire décrit par `entry`
+    /// 
+    /// retourne `NotADirectory` si l'entrée désigne un fichier.
+    pub fn list_subdir(&self, entry: &DirEntry) -> Result<Vec<DirItem>, Fat32Error<D::Error>> {
+        if !entry.is_directory() {
+            return Err(Fat32Error::NotADirectory);
+        }
+        let cluster = entry.first_cluster();
+        self.list_dir(if cluster < 2 { self.boot_sector.root_cluster } else { cluster })
+    }
+    
+    /// lit le contenu complet d'un fichier
+    /// 
+    /// la taille allouée est bornée par la longueur de la chaîne : une
+    /// entrée corrompue ne peut pas réclamer plus que ses clusters.
+    pub fn read_file_to_vec(&self, entry: &DirEntry) -> Result<Vec<u8>, Fat32Error<D::Error>> {
+        if entry.file_size == 0 || entry.first_cluster() == 0 {
+            return Ok(Vec::new());
+        }
+        let clusters = self.chain_length(entry.first_cluster())?;
+        let size = (entry.file_size as u64).min(clusters as u64 * self.boot_sector.cluster_size() as u64);
+        let mut data = alloc::vec![0u8; size as usize];
+        
+        let read = self.read_file(entry.first_cluster(), &mut data)?;
+        data.truncate(read);
+        Ok(data)
+    }
+}
+
+/// reconstitue le nom long porté par les entrées LFN précédant `entry`
+/// 
+/// les entrées sont dans l'ordre du disque : la dernière lue est la
+/// première partie du nom.
+fn long_name(parts: &[LfnEntry], entry: &DirEntry) -> Option<String> {
+    let checksum = lfn_checksum(&entry.name);
+    if parts.is_empty() || parts.iter().any(|part| part.checksum != checksum) {
+        return None;
+    }
+    
+    let mut name = LongName::new();
+    for (sequence, part) in parts.iter().rev().enumerate() {
+        name.set_part(sequence + 1, &part.chars());
+    }
+    
+    Some(name.to_string_lossy())
+}
@@ -3,4 +3,20 @@
 pub mod parser;
 pub mod file_ops;
 pub mod file_info;
+pub mod directory;
+pub mod relocate;
+pub mod resize;
+pub mod defrag;
+pub mod undelete;
+pub mod carving;
+pub mod slack;
+pub mod truncation;
+pub mod bitmap;
+pub mod allocator;
+pub mod fat_scan;
+pub mod volume_state;
+pub(crate) mod steps;
 
+pub mod async_parser;
+#[cfg(feature = "alloc")]
+pub mod listing;
@@ -1,27 +1,41 @@
 //! parser principal FAT32
 
 use crate::structures::boot_sector::BootSector;
-use crate::traits::block_device::BlockDevice;
+use crate::structures::geometry::Geometry;
+use crate::traits::block_device::{BlockDevice, BlockDeviceMut, SectorKind};
 use crate::structures::dir_entry::DirEntry;
 use crate::utils::error::Fat32Error;
 use crate::utils::fat;
 use crate::operations::file_info::FileInfo;
 use crate::structures::fsinfo::FSInfo;
+use crate::operations::bitmap::FreeClusterBitmap;
+use crate::operations::allocator::AllocationStrategy;
+use crate::operations::volume_state::VolumeFlags;
+use crate::utils::validator::{MountOptions, ValidationReport};
+use crate::operations::steps::{self, ChainWalk, ClusterHead, FileRead, FileWrite, HeadPart, ReadStep, WriteStep};
 
 /// parser FAT32
 /// 
 /// structure principale pour interagir avec un système de fichiers FAT32.
 /// fonctionne avec n'importe quel dispositif implémentant le trait BlockDevice.
+/// les opérations qui modifient le volume ne sont disponibles que si le
+/// dispositif implémente aussi `BlockDeviceMut` : un parser sur une image
+/// ouverte en lecture seule ne peut pas l'écrire par erreur.
 /// 
 /// # Exemples
 /// 
 /// ```no_run
 /// use fat32_parser::parser::Fat32Parser;
 /// use fat32_parser::block_device::BlockDevice;
+/// # use fat32_parser::error::Fat32Error;
+/// # fn exemple<D: BlockDevice>(mon_device: D, mon_disque: D, debut_partition: u64, cluster: u32) -> Result<(), Fat32Error<D::Error>> {
 /// 
 /// // créer un parser avec un device
 /// let mut parser = Fat32Parser::new(mon_device)?;
 /// 
+/// // ou sur une partition d'un disque complet
+/// let mut parser = Fat32Parser::new_at(mon_disque, debut_partition)?;
+/// 
 /// // charger FSInfo
 /// parser.load_fsinfo()?;
 /// 
@@ -34,30 +48,74 @@ use crate::structures::fsinfo::FSInfo;
 /// // lire un fichier complet
 /// let mut buffer = [0u8; 4096];
 /// let bytes_read = parser.read_file(cluster, &mut buffer)?;
+/// # Ok(())
+/// # }
 /// ```
-pub struct Fat32Parser<D: BlockDevice> {
-    device: D,
-    boot_sector: BootSector,
-    fsinfo: Option<FSInfo>,
+pub struct Fat32Parser<'a, D: BlockDevice> {
+    pub(crate) device: D,
+    pub(crate) boot_sector: BootSector,
+    /// géométrie dérivée du boot sector, à recalculer s'il change
+    pub(crate) geometry: Geometry,
+    pub(crate) fsinfo: Option<FSInfo>,
+    pub(crate) free_bitmap: Option<FreeClusterBitmap<'a>>,
+    pub(crate) allocator: AllocationStrategy,
+    pub(crate) volume_flags: VolumeFlags,
+    /// le bit de démontage propre a été retiré par ce parser
+    pub(crate) dirty: bool,
+    /// premier secteur du volume sur le dispositif
+    pub(crate) partition_start: u64,
+    /// problèmes relevés dans le boot sector au montage
+    pub(crate) validation: ValidationReport,
+    /// secteurs disponibles sur le dispositif à partir du début du volume
+    pub(crate) device_sectors: Option<u64>,
 }
 
-impl<D: BlockDevice> Fat32Parser<D> {
-    /// crée un nouveau parser
-    pub fn new(device: D) -> Result<Self, Fat32Error> {
+impl<D: BlockDevice> Fat32Parser<'_, D> {
+    /// crée un nouveau parser pour un volume commençant au secteur 0
+    pub fn new(device: D) -> Result<Self, Fat32Error<D::Error>> {
+        Self::new_at(device, 0)
+    }
+    
+    /// crée un parser pour un volume commençant au secteur
+    /// `partition_start` du dispositif
+    /// 
+    /// tous les numéros de secteur manipulés par le parser restent
+    /// relatifs au volume ; le décalage n'est ajouté qu'à l'appel du
+    /// dispositif. le boot sector est validé avec les options par défaut
+    /// (mode strict). le champ `hidden_sectors` du boot sector n'est pas
+    /// utilisé : sur un disque complet, le début de la partition vient de
+    /// la table de partitions.
+    pub fn new_at(device: D, partition_start: u64) -> Result<Self, Fat32Error<D::Error>> {
+        Self::mount_at(device, partition_start, MountOptions::default())
+    }
+    
+    /// monte le volume commençant au secteur `partition_start`
+    /// 
+    /// le boot sector est validé, et le montage refusé selon
+    /// `options.strictness` ; le rapport complet reste disponible par
+    /// `validation`. un volume plus grand que le dispositif (image
+    /// tronquée) n'est accepté qu'en mode tolérant, voir `is_truncated`.
+    pub fn mount_at(device: D, partition_start: u64, options: MountOptions) -> Result<Self, Fat32Error<D::Error>> {
         let mut buffer = [0u8; 512];
-        device.read_sector(0, &mut buffer)?;
+        device.read_sector(partition_start, &mut buffer)?;
+        let mount = steps::mount(&buffer, partition_start, device.sector_count(), options)?;
         
-        let boot_sector = unsafe { BootSector::from_bytes(&buffer) };
-        
-        if !boot_sector.is_valid() {
-            return Err(Fat32Error::InvalidSignature);
-        }
-        
-        Ok(Self {
+        let mut parser = Self {
             device,
-            boot_sector,
+            boot_sector: mount.boot_sector,
+            geometry: mount.boot_sector.geometry(),
             fsinfo: None,
-        })
+            free_bitmap: None,
+            allocator: AllocationStrategy::default(),
+            volume_flags: VolumeFlags::from_fat_entry(0),
+            dirty: false,
+            partition_start,
+            validation: mount.validation,
+            device_sectors: mount.device_sectors,
+        };
+        parser.volume_flags = VolumeFlags::from_fat_entry(parser.read_fat1()?);
+        
+        Ok(parser)
     }
     
     /// retourne le boot sector
@@ -65,16 +123,60 @@ impl<D: BlockDevice> Fat32Parser<D> {
         &self.boot_sector
     }
     
+    /// retourne la géométrie du volume
+    pub fn geometry(&self) -> &Geometry {
+        &self.geometry
+    }
+    
+    /// retourne le premier secteur du volume sur le dispositif
+    pub fn partition_start(&self) -> u64 {
+        self.partition_start
+    }
+    
+    /// retourne les problèmes relevés dans le boot sector au montage
+    pub fn validation(&self) -> &ValidationReport {
+        &self.validation
+    }
+    
+    /// retourne le nombre de secteurs disponibles sur le dispositif à
+    /// partir du début du volume, s'il est connu
+    pub fn device_sectors(&self) -> Option<u64> {
+        self.device_sectors
+    }
+    
+    /// rend le dispositif sans rien écrire
+    /// 
+    /// sur un dispositif accessible en écriture, préférer `unmount`, qui
+    /// écrit les données en attente et marque le volume propre.
+    pub fn into_inner(self) -> D {
+        self.device
+    }
+    
+    /// lit un secteur du volume
+    pub(crate) fn read_sector(&self, sector: u32, buffer: &mut [u8]) -> Result<(), Fat32Error<D::Error>> {
+        self.device.read_sector(self.partition_start + sector as u64, buffer)
+    }
+    
+    /// lit des secteurs consécutifs du volume
+    pub(crate) fn read_sectors(&self, start: u32, buffer: &mut [u8]) -> Result<(), Fat32Error<D::Error>> {
+        self.device.read_sectors(self.partition_start + start as u64, buffer)
+    }
+    
+    /// retourne le plus grand numéro de cluster de données valide
+    pub fn max_cluster(&self) -> u32 {
+        self.geometry.max_cluster()
+    }
+    
     /// charge FSInfo
-    pub fn load_fsinfo(&mut self) -> Result<(), Fat32Error> {
+    pub fn load_fsinfo(&mut self) -> Result<(), Fat32Error<D::Error>> {
         let mut buffer = [0u8; 512];
         let fsinfo_sector = self.boot_sector.fs_info_sector as u32;
-        self.device.read_sector(fsinfo_sector, &mut buffer)?;
+        self.read_sector(fsinfo_sector, &mut buffer)?;
         
-        let fsinfo = unsafe { FSInfo::from_bytes(&buffer) };
+        let fsinfo = FSInfo::parse(&buffer).map_err(Fat32Error::widen)?;
         
         if !fsinfo.is_valid() {
-            return Err(Fat32Error::InvalidSignature);
+            return Err(Fat32Error::InvalidSignature { sector: self.partition_start + fsinfo_sector as u64 });
         }
         
         self.fsinfo = Some(fsinfo);
@@ -82,76 +184,66 @@ impl<D: BlockDevice> Fat32Parser<D> {
     }
     
     /// lit une entrée de la FAT
-    pub fn read_fat_entry(&self, cluster: u32) -> Result<u32, Fat32Error> {
-        if cluster < 2 {
-            return Err(Fat32Error::InvalidCluster);
-        }
-        
-        let fat_offset = cluster * 4;
-        let fat_sector = self.boot_sector.fat_start_sector() + (fat_offset / 512);
-        let entry_offset = (fat_offset % 512) as usize;
-        
+    /// 
+    /// retourne `InvalidCluster` si le cluster est hors du volume.
+    pub fn read_fat_entry(&self, cluster: u32) -> Result<u32, Fat32Error<D::Error>> {
+        let (sector, offset) = self.geometry.fat_entry_position(cluster)
+            .ok_or(Fat32Error::InvalidCluster { cluster })?;
         let mut buffer = [0u8; 512];
-        self.device.read_sector(fat_sector, &mut buffer)?;
-        
-        let entry = u32::from_le_bytes([
-            buffer[entry_offset],
-            buffer[entry_offset + 1],
-            buffer[entry_offset + 2],
-            buffer[entry_offset + 3],
-        ]) & 0x0FFFFFFF;
+        self.read_sector(sector, &mut buffer)?;
         
-        Ok(entry)
+        Ok(fat::read_entry(&buffer, offset))
+    }
+    
+    /// premier secteur d'un cluster de données
+    /// 
+    /// retourne `InvalidCluster` pour un cluster hors de la zone de
+    /// données, par exemple lu dans une entrée corrompue.
+    pub(crate) fn cluster_sector(&self, cluster: u32) -> Result<u32, Fat32Error<D::Error>> {
+        self.geometry.cluster_to_sector(cluster).ok_or(Fat32Error::InvalidCluster { cluster })
     }
     
     /// lit un cluster complet
-    pub fn read_cluster(&self, cluster: u32, buffer: &mut [u8]) -> Result<(), Fat32Error> {
-        let first_sector = self.boot_sector.cluster_to_sector(cluster);
-        let sectors_per_cluster = self.boot_sector.sectors_per_cluster as u32;
-        
-        for i in 0..sectors_per_cluster {
-            let offset = (i * 512) as usize;
-            self.device.read_sector(first_sector + i, &mut buffer[offset..offset + 512])?;
+    pub fn read_cluster(&self, cluster: u32, buffer: &mut [u8]) -> Result<(), Fat32Error<D::Error>> {
+        self.read_run(cluster, 1, buffer)
+    }
+    
+    /// lit `count` clusters contigus en un seul transfert
+    pub fn read_run(&self, first_cluster: u32, count: u32, buffer: &mut [u8]) -> Result<(), Fat32Error<D::Error>> {
+        let (first_sector, bytes) = steps::read_run_transfer(&self.geometry, first_cluster, count, buffer.len())?;
+        self.read_sectors(first_sector, &mut buffer[..bytes])
+    }
+    
+    /// lit le début d'un cluster, `buffer` étant plus court qu'un cluster
+    fn read_cluster_head(&self, cluster: u32, buffer: &mut [u8]) -> Result<(), Fat32Error<D::Error>> {
+        for part in ClusterHead::read(&self.geometry, cluster, buffer.len())? {
+            match part {
+                HeadPart::Sectors { sector, range } => self.read_sectors(sector, &mut buffer[range])?,
+                HeadPart::Partial { sector, range } => {
+                    let mut data = [0u8; 512];
+                    self.read_sector(sector, &mut data)?;
+                    buffer[range.clone()].copy_from_slice(&data[..range.len()]);
+                }
+            }
         }
-        
         Ok(())
     }
     
-    /// lit les entrées du répertoire racine
-    pub fn read_root_dir(&self) -> Result<[DirEntry; 16], Fat32Error> {
-        let root_cluster = self.boot_sector.root_cluster;
-        let cluster_size = self.boot_sector.cluster_size() as usize;
+    /// lit les entrées du premier secteur du répertoire racine
+    pub fn read_root_dir(&self) -> Result<[DirEntry; 16], Fat32Error<D::Error>> {
+        let mut buffer = [0u8; 512];
+        self.read_sector(self.cluster_sector(self.boot_sector.root_cluster)?, &mut buffer)?;
         
-        let mut buffer = [0u8; 4096];
-        self.read_cluster(root_cluster, &mut buffer[..cluster_size])?;
-        
-        let mut entries = [DirEntry {
-            name: [0; 11],
-            attributes: 0,
-            nt_reserved: 0,
-            creation_time_tenth: 0,
-            creation_time: 0,
-            creation_date: 0,
-            last_access_date: 0,
-            first_cluster_high: 0,
-            last_write_time: 0,
-            last_write_date: 0,
-            first_cluster_low: 0,
-            file_size: 0,
-        }; 16];
-        
-        for i in 0..16 {
-            let offset = i * 32;
-            entries[i] = unsafe {
-                core::ptr::read_unaligned(buffer[offset..].as_ptr() as *const DirEntry)
-            };
+        let mut entries = [DirEntry::from_bytes(&[0; 32]); 16];
+        for (slot, raw) in entries.iter_mut().zip(buffer.chunks_exact(32)) {
+            *slot = DirEntry::parse(raw).map_err(Fat32Error::widen)?;
         }
         
         Ok(entries)
     }
     
     /// suit une chaîne de clusters
-    pub fn follow_cluster_chain(&self, start_cluster: u32) -> Result<[u32; 128], Fat32Error> {
+    pub fn follow_cluster_chain(&self, start_cluster: u32) -> Result<[u32; 128], Fat32Error<D::Error>> {
         let mut chain = [0u32; 128];
         let mut current = start_cluster;
         let mut count = 0;
@@ -165,32 +257,43 @@ impl<D: BlockDevice> Fat32Parser<D> {
         Ok(chain)
     }
     
-    /// retourne le FSInfo
-    pub fn fsinfo(&self) -> Option<&FSInfo> {
-        self.fsinfo.as_ref()
+    /// retourne le nombre de clusters d'une chaîne
+    pub fn chain_length(&self, head: u32) -> Result<u32, Fat32Error<D::Error>> {
+        let mut walk = ChainWalk::new(head, self.max_cluster());
+        let mut length = 0;
+        
+        while let Some(cluster) = walk.current()? {
+            length += 1;
+            walk.advance(self.read_fat_entry(cluster)?);
+        }
+        
+        Ok(length)
     }
     
-    /// écrit dans un cluster
-    pub fn write_cluster(&mut self, cluster: u32, data: &[u8]) -> Result<(), Fat32Error> {
-        let first_sector = self.boot_sector.cluster_to_sector(cluster);
-        let sectors_per_cluster = self.boot_sector.sectors_per_cluster as u32;
-        
-        for i in 0..sectors_per_cluster {
-            let offset = (i * 512) as usize;
-            let end = offset + 512;
-            if end <= data.len() {
-                self.device.write_sector(first_sector + i, &data[offset..end])?;
+    /// retourne le cluster à la position `position` d'une chaîne
+    pub fn chain_cluster_at(&self, head: u32, position: u32) -> Result<u32, Fat32Error<D::Error>> {
+        let mut current = head;
+        for _ in 0..position {
+            current = self.read_fat_entry(current)?;
+            if fat::is_eoc(current) || current < 2 || current > self.max_cluster() {
+                return Err(Fat32Error::BadChain { cluster: current });
             }
         }
-        
-        Ok(())
+        Ok(current)
+    }
+    
+    /// retourne le FSInfo
+    pub fn fsinfo(&self) -> Option<&FSInfo> {
+        self.fsinfo.as_ref()
     }
     
     /// trouve un cluster libre
-    pub fn find_free_cluster(&self) -> Result<u32, Fat32Error> {
-        let total_clusters = self.boot_sector.total_sectors_32 / self.boot_sector.sectors_per_cluster as u32;
+    pub fn find_free_cluster(&self) -> Result<u32, Fat32Error<D::Error>> {
+        if let Some(bitmap) = self.free_bitmap.as_ref() {
+            return bitmap.find_free_from(2).ok_or(Fat32Error::NotFound);
+        }
         
-        for cluster in 2..total_clusters {
+        for cluster in 2..=self.max_cluster() {
             let entry = self.read_fat_entry(cluster)?;
             if fat::is_free(entry) {
                 return Ok(cluster);
@@ -200,73 +303,18 @@ impl<D: BlockDevice> Fat32Parser<D> {
         Err(Fat32Error::NotFound)
     }
     
-    /// écrit une entrée dans la FAT
-    pub fn write_fat_entry(&mut self, cluster: u32, value: u32) -> Result<(), Fat32Error> {
-        if cluster < 2 {
-            return Err(Fat32Error::InvalidCluster);
-        }
-        
-        let fat_offset = cluster * 4;
-        let fat_sector = self.boot_sector.fat_start_sector() + (fat_offset / 512);
-        let entry_offset = (fat_offset % 512) as usize;
-        
-        let mut buffer = [0u8; 512];
-        self.device.read_sector(fat_sector, &mut buffer)?;
-        
-        let masked_value = value & 0x0FFFFFFF;
-        let bytes = masked_value.to_le_bytes();
-        buffer[entry_offset] = bytes[0];
-        buffer[entry_offset + 1] = bytes[1];
-        buffer[entry_offset + 2] = bytes[2];
-        buffer[entry_offset + 3] = bytes[3];
-        
-        self.device.write_sector(fat_sector, &buffer)?;
-        
-        Ok(())
-    }
-    
-    /// alloue un nouveau cluster
-    pub fn allocate_cluster(&mut self, prev_cluster: Option<u32>) -> Result<u32, Fat32Error> {
-        let new_cluster = self.find_free_cluster()?;
-        
-        self.write_fat_entry(new_cluster, crate::fat::FAT_EOC)?;
-        
-        if let Some(prev) = prev_cluster {
-            self.write_fat_entry(prev, new_cluster)?;
-        }
-        
-        Ok(new_cluster)
-    }
-    
-    /// libère un cluster
-    pub fn free_cluster(&mut self, cluster: u32) -> Result<(), Fat32Error> {
-        self.write_fat_entry(cluster, crate::fat::FAT_FREE)
-    }
-    
-    /// libère une chaîne de clusters
-    pub fn free_cluster_chain(&mut self, start_cluster: u32) -> Result<(), Fat32Error> {
-        let mut current = start_cluster;
-        
-        while !fat::is_eoc(current) && !fat::is_free(current) {
-            let next = self.read_fat_entry(current)?;
-            self.free_cluster(current)?;
-            current = next;
+    /// compte le nombre de clusters libres
+    pub fn count_free_clusters(&self) -> Result<u32, Fat32Error<D::Error>> {
+        if let Some(bitmap) = self.free_bitmap.as_ref() {
+            return Ok(bitmap.free_count());
         }
         
-        Ok(())
-    }
-    
-    /// compte le nombre de clusters libres
-    pub fn count_free_clusters(&self) -> Result<u32, Fat32Error> {
-        let total_clusters = self.boot_sector.total_sectors_32 / self.boot_sector.sectors_per_cluster as u32;
         let mut count = 0;
-        
-        for cluster in 2..total_clusters {
-            let entry = self.read_fat_entry(cluster)?;
+        self.scan_fat(|_, entry| {
             if fat::is_free(entry) {
                 count += 1;
             }
-        }
+        })?;
         
         Ok(count)
     }
@@ -274,11 +322,11 @@ impl<D: BlockDevice> Fat32Parser<D> {
     /// retourne la taille d'un fichier en clusters
     pub fn file_size_in_clusters(&self, size: u32) -> u32 {
         let cluster_size = self.boot_sector.cluster_size();
-        (size + cluster_size - 1) / cluster_size
+        size.div_ceil(cluster_size)
     }
     
     /// liste les fichiers du répertoire racine
-    pub fn list_root_files(&self) -> Result<[Option<FileInfo>; 16], Fat32Error> {
+    pub fn list_root_files(&self) -> Result<[Option<FileInfo>; 16], Fat32Error<D::Error>> {
         let entries = self.read_root_dir()?;
         let mut files = [None; 16];
         
@@ -292,60 +340,214 @@ impl<D: BlockDevice> Fat32Parser<D> {
     }
     
     /// lit un fichier complet en suivant la chaîne de clusters
-    pub fn read_file(&self, start_cluster: u32, buffer: &mut [u8]) -> Result<usize, Fat32Error> {
-        let cluster_size = self.boot_sector.cluster_size() as usize;
-        let mut current_cluster = start_cluster;
-        let mut offset = 0;
-        
-        while !fat::is_eoc(current_cluster) && offset < buffer.len() {
-            let read_size = core::cmp::min(cluster_size, buffer.len() - offset);
-            
-            if read_size == cluster_size {
-                self.read_cluster(current_cluster, &mut buffer[offset..offset + cluster_size])?;
-            } else {
-                let mut temp = [0u8; 4096];
-                self.read_cluster(current_cluster, &mut temp[..cluster_size])?;
-                buffer[offset..offset + read_size].copy_from_slice(&temp[..read_size]);
+    /// 
+    /// un fichier vide (cluster 0) donne 0 octet ; un cluster hors du
+    /// volume en cours de chaîne donne `BadChain`.
+    pub fn read_file(&self, start_cluster: u32, buffer: &mut [u8]) -> Result<usize, Fat32Error<D::Error>> {
+        let mut read = FileRead::new(start_cluster, buffer.len(), self.boot_sector.cluster_size(), self.max_cluster());
+        let mut entry = None;
+        
+        loop {
+            match read.step(entry.take())? {
+                ReadStep::Fat(cluster) => entry = Some(self.read_fat_entry(cluster)?),
+                ReadStep::Run { first, count, range } => self.read_run(first, count, &mut buffer[range])?,
+                ReadStep::Head { cluster, range } => self.read_cluster_head(cluster, &mut buffer[range])?,
+                ReadStep::Done(bytes) => return Ok(bytes),
             }
-            
-            offset += read_size;
-            current_cluster = self.read_fat_entry(current_cluster)?;
+        }
+    }
+}
+
+// opérations qui modifient le volume
+impl<D: BlockDeviceMut> Fat32Parser<'_, D> {
+    /// écrit des secteurs de données consécutifs du volume
+    pub(crate) fn write_sectors(&mut self, start: u32, buffer: &[u8]) -> Result<(), Fat32Error<D::Error>> {
+        self.device.write_sectors(self.partition_start + start as u64, buffer)
+    }
+    
+    /// écrit un secteur du volume en précisant sa nature
+    pub(crate) fn write_sector_kind(&mut self, sector: u32, buffer: &[u8], kind: SectorKind) -> Result<(), Fat32Error<D::Error>> {
+        self.device.write_sector_kind(self.partition_start + sector as u64, buffer, kind)
+    }
+    
+    /// écrit le boot sector en mémoire sur le disque (et sa copie de secours)
+    pub fn write_boot_sector(&mut self) -> Result<(), Fat32Error<D::Error>> {
+        let bytes = self.boot_sector.to_bytes();
+        self.write_sector_kind(0, &bytes, SectorKind::Directory)?;
+        
+        let backup = self.boot_sector.backup_boot_sector as u32;
+        if backup != 0 {
+            self.write_sector_kind(backup, &bytes, SectorKind::Directory)?;
         }
         
-        Ok(offset)
+        Ok(())
     }
     
-    /// écrit un fichier complet
-    pub fn write_file(&mut self, start_cluster: u32, data: &[u8]) -> Result<(), Fat32Error> {
-        let cluster_size = self.boot_sector.cluster_size() as usize;
-        let mut current_cluster = start_cluster;
-        let mut offset = 0;
-        
-        while offset < data.len() {
-            let write_size = core::cmp::min(cluster_size, data.len() - offset);
-            
-            if write_size == cluster_size {
-                self.write_cluster(current_cluster, &data[offset..offset + cluster_size])?;
-            } else {
-                let mut temp = [0u8; 4096];
-                temp[..write_size].copy_from_slice(&data[offset..offset + write_size]);
-                self.write_cluster(current_cluster, &temp[..cluster_size])?;
-            }
-            
-            offset += write_size;
-            
-            if offset < data.len() {
-                let next = self.read_fat_entry(current_cluster)?;
-                if fat::is_eoc(next) {
-                    let new_cluster = self.allocate_cluster(Some(current_cluster))?;
-                    current_cluster = new_cluster;
-                } else {
-                    current_cluster = next;
+    /// écrit sur le support les secteurs encore en attente dans le dispositif
+    /// 
+    /// nécessaire avec un cache différé avant de retirer le support. le
+    /// volume est remis à l'état « démonté proprement », une fois les
+    /// données et métadonnées en attente sur le support : une coupure ne
+    /// laisse jamais un volume marqué propre mais incomplet.
+    pub fn flush(&mut self) -> Result<(), Fat32Error<D::Error>> {
+        self.store_next_free()?;
+        self.device.flush()?;
+        self.mark_clean()?;
+        self.device.flush()
+    }
+    
+    /// marque le nombre de clusters libres de FSInfo comme inconnu
+    /// 
+    /// à appeler quand une opération change le nombre de clusters sans
+    /// tenir le compteur à jour.
+    pub(crate) fn invalidate_free_count(&mut self) -> Result<(), Fat32Error<D::Error>> {
+        let sector = self.boot_sector.fs_info_sector as u32;
+        if sector == 0 || sector == 0xFFFF {
+            return Ok(());
+        }
+        
+        let mut buffer = [0u8; 512];
+        self.read_sector(sector, &mut buffer)?;
+        
+        let mut fsinfo = FSInfo::parse(&buffer).map_err(Fat32Error::widen)?;
+        if !fsinfo.is_valid() {
+            return Ok(());
+        }
+        
+        fsinfo.free_count = 0xFFFFFFFF;
+        self.write_sector_kind(sector, &fsinfo.to_bytes(), SectorKind::Directory)?;
+        
+        if let Some(loaded) = self.fsinfo.as_mut() {
+            loaded.free_count = 0xFFFFFFFF;
+        }
+        
+        Ok(())
+    }
+    
+    /// écrit dans FSInfo le prochain cluster libre retenu en mémoire
+    /// 
+    /// les allocations ne font que le noter ; il est écrit au flush, s'il
+    /// a changé, pour que `NextFit` reprenne au même endroit au montage
+    /// suivant.
+    fn store_next_free(&mut self) -> Result<(), Fat32Error<D::Error>> {
+        let sector = self.boot_sector.fs_info_sector as u32;
+        let next_free = match self.fsinfo.as_ref() {
+            Some(loaded) if sector != 0 && sector != 0xFFFF => loaded.next_free,
+            _ => return Ok(()),
+        };
+        
+        let mut buffer = [0u8; 512];
+        self.read_sector(sector, &mut buffer)?;
+        
+        let mut fsinfo = FSInfo::parse(&buffer).map_err(Fat32Error::widen)?;
+        if !fsinfo.is_valid() || fsinfo.next_free == next_free {
+            return Ok(());
+        }
+        
+        fsinfo.next_free = next_free;
+        self.mark_dirty()?;
+        self.write_sector_kind(sector, &fsinfo.to_bytes(), SectorKind::Directory)
+    }
+    
+    /// écrit le début d'un cluster et complète le reste par des zéros
+    fn write_cluster_head(&mut self, cluster: u32, data: &[u8]) -> Result<(), Fat32Error<D::Error>> {
+        let parts = ClusterHead::write(&self.geometry, cluster, data.len())?;
+        self.mark_dirty()?;
+        
+        for part in parts {
+            match part {
+                HeadPart::Sectors { sector, range } => self.write_sectors(sector, &data[range])?,
+                HeadPart::Partial { sector, range } => {
+                    let mut padded = [0u8; 512];
+                    padded[..range.len()].copy_from_slice(&data[range]);
+                    self.write_sectors(sector, &padded)?;
                 }
             }
         }
+        Ok(())
+    }
+    
+    /// écrit des clusters contigus en un seul transfert
+    /// 
+    /// seuls les secteurs complets de `data` sont écrits.
+    pub fn write_run(&mut self, first_cluster: u32, data: &[u8]) -> Result<(), Fat32Error<D::Error>> {
+        let (first_sector, whole) = steps::write_run_transfer(&self.geometry, first_cluster, data.len())?;
+        self.mark_dirty()?;
+        if whole == 0 {
+            return Ok(());
+        }
+        
+        self.write_sectors(first_sector, &data[..whole])
+    }
+    
+    /// écrit dans un cluster
+    /// 
+    /// `data` est tronqué à la taille d'un cluster ; comme pour
+    /// `write_run`, seuls ses secteurs complets sont écrits.
+    pub fn write_cluster(&mut self, cluster: u32, data: &[u8]) -> Result<(), Fat32Error<D::Error>> {
+        let cluster_size = self.boot_sector.cluster_size() as usize;
+        self.write_run(cluster, &data[..data.len().min(cluster_size)])
+    }
+    
+    /// écrit une entrée dans la FAT
+    pub fn write_fat_entry(&mut self, cluster: u32, value: u32) -> Result<(), Fat32Error<D::Error>> {
+        let (fat_sector, offset) = self.geometry.fat_entry_position(cluster)
+            .ok_or(Fat32Error::InvalidCluster { cluster })?;
+        self.mark_dirty()?;
+        
+        let mut buffer = [0u8; 512];
+        self.read_sector(fat_sector, &mut buffer)?;
+        
+        fat::write_entry(&mut buffer, offset, value);
+        self.write_sector_kind(fat_sector, &buffer, SectorKind::Fat)?;
+        
+        if let Some(bitmap) = self.free_bitmap.as_mut() {
+            bitmap.set_free(cluster, fat::is_free(fat::mask_cluster(value)));
+        }
         
         Ok(())
     }
+    
+    /// alloue un nouveau cluster
+    /// 
+    /// l'emplacement est choisi par la stratégie du parser (voir
+    /// `set_allocation_strategy`), par défaut le premier cluster libre.
+    pub fn allocate_cluster(&mut self, prev_cluster: Option<u32>) -> Result<u32, Fat32Error<D::Error>> {
+        self.allocate_run(prev_cluster, 1)
+    }
+    
+    /// libère un cluster
+    pub fn free_cluster(&mut self, cluster: u32) -> Result<(), Fat32Error<D::Error>> {
+        self.write_fat_entry(cluster, crate::fat::FAT_FREE)
+    }
+    
+    /// libère une chaîne de clusters
+    pub fn free_cluster_chain(&mut self, start_cluster: u32) -> Result<(), Fat32Error<D::Error>> {
+        let mut walk = ChainWalk::releasing(start_cluster, self.max_cluster());
+        
+        while let Some(cluster) = walk.current()? {
+            let next = self.read_fat_entry(cluster)?;
+            self.free_cluster(cluster)?;
+            walk.advance(next);
+        }
+        
+        Ok(())
+    }
+    
+    /// écrit un fichier complet
+    pub fn write_file(&mut self, start_cluster: u32, data: &[u8]) -> Result<(), Fat32Error<D::Error>> {
+        let mut write = FileWrite::new(start_cluster, data.len(), self.boot_sector.cluster_size());
+        let mut answer = None;
+        
+        loop {
+            match write.step(answer.take()) {
+                WriteStep::Cluster { cluster, range } => self.write_cluster(cluster, &data[range])?,
+                WriteStep::Head { cluster, range } => self.write_cluster_head(cluster, &data[range])?,
+                WriteStep::Fat(cluster) => answer = Some(self.read_fat_entry(cluster)?),
+                WriteStep::Allocate(previous) => answer = Some(self.allocate_cluster(Some(previous))?),
+                WriteStep::Done => return Ok(()),
+            }
+        }
+    }
 }
 
@@ -0,0 +1,543 @@
+//! déplacement de clusters
+//! 
+//! primitives pour déplacer le contenu d'un cluster vers un autre en
+//! gardant le système de fichiers cohérent : la chaîne FAT et les
+//! entrées de répertoire qui référencent le cluster sont mises à jour.
+//! 
+//! retrouver la référence d'un cluster demande de lire toute la FAT et
+//! de parcourir l'arborescence. une opération qui déplace beaucoup de
+//! clusters construit une seule fois une `ClusterReferences`, tenue à
+//! jour au fil des déplacements, ou passe directement la référence
+//! qu'elle connaît à `relocate_cluster_from`.
+
+#[cfg(feature = "alloc")]
+use alloc::collections::{BTreeMap, BTreeSet};
+#[cfg(feature = "alloc")]
+use alloc::vec::Vec;
+use crate::operations::directory::{EntryLocation, Visit, ENTRIES_PER_SECTOR};
+use crate::operations::parser::Fat32Parser;
+use crate::structures::dir_entry::DirEntry;
+use crate::traits::block_device::{BlockDevice, BlockDeviceMut};
+use crate::utils::constants::ENTRY_EMPTY;
+use crate::utils::error::Fat32Error;
+use crate::utils::fat;
+
+/// nombre de secteurs copiés par transfert
+pub(crate) const COPY_BATCH: usize = 8;
+
+/// ce qui désigne un cluster, et doit suivre son déplacement
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ClusterOwner {
+    /// cluster qui le précède dans sa chaîne
+    Predecessor(u32),
+    /// entrée de répertoire dont il est le premier cluster
+    Entry(EntryLocation),
+    /// premier cluster du répertoire racine
+    Root,
+    /// début d'une chaîne perdue, qu'aucune entrée ne désigne
+    Lost,
+}
+
+/// chaîne de l'arborescence
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Chain {
+    /// premier cluster
+    pub head: u32,
+    /// la chaîne est celle d'un répertoire
+    pub is_directory: bool,
+}
+
+/// table inverse des références aux clusters
+/// 
+/// avec la feature `alloc`, elle est construite par `cluster_references`
+/// en une lecture de la FAT et un parcours de l'arborescence, et donne
+/// pour chaque cluster son prédécesseur ou l'entrée qui le désigne. elle
+/// occupe 4 octets par cluster. sans `alloc`, elle est vide et chaque
+/// recherche relit la FAT et l'arborescence.
+#[derive(Debug, Default)]
+pub struct ClusterReferences {
+    /// prédécesseur de chaque cluster, 0 pour un début de chaîne
+    #[cfg(feature = "alloc")]
+    predecessors: Vec<u32>,
+    /// chaînes dans l'ordre du parcours, racine en tête
+    #[cfg(feature = "alloc")]
+    chains: Vec<Chain>,
+    /// racine ou entrée qui désigne chaque chaîne, à la même position
+    #[cfg(feature = "alloc")]
+    owners: Vec<ClusterOwner>,
+    /// premier cluster vers la position de sa chaîne dans `chains`
+    #[cfg(feature = "alloc")]
+    heads: BTreeMap<u32, usize>,
+    /// cluster de répertoire contenant une entrée, et position de sa
+    /// chaîne
+    #[cfg(feature = "alloc")]
+    entries: BTreeSet<(u32, usize)>,
+}
+
+impl ClusterReferences {
+    /// retourne la référence connue d'un cluster
+    #[cfg(feature = "alloc")]
+    pub fn owner(&self, cluster: u32) -> Option<ClusterOwner> {
+        match self.predecessors.get(cluster as usize) {
+            Some(&previous) if previous != 0 => Some(ClusterOwner::Predecessor(previous)),
+            _ => self.heads.get(&cluster).map(|&index| self.owners[index]),
+        }
+    }
+    
+    /// retourne la référence connue d'un cluster
+    #[cfg(not(feature = "alloc"))]
+    pub fn owner(&self, _cluster: u32) -> Option<ClusterOwner> {
+        None
+    }
+    
+    /// enregistre la nouvelle position de l'entrée d'indice `index`
+    #[cfg(feature = "alloc")]
+    fn locate(&mut self, index: usize, location: EntryLocation) {
+        if let ClusterOwner::Entry(previous) = self.owners[index] {
+            self.entries.remove(&(previous.cluster, index));
+        }
+        self.owners[index] = ClusterOwner::Entry(location);
+        self.entries.insert((location.cluster, index));
+    }
+    
+    /// enregistre le déplacement de `old` vers `new`
+    /// 
+    /// `next` est le cluster suivant dans la chaîne, et `offset` l'écart
+    /// entre les premiers secteurs des deux clusters.
+    #[cfg(feature = "alloc")]
+    fn moved(&mut self, old: u32, new: u32, next: u32, offset: i64) {
+        let previous = core::mem::take(&mut self.predecessors[old as usize]);
+        self.predecessors[new as usize] = previous;
+        if next >= 2 {
+            if let Some(slot) = self.predecessors.get_mut(next as usize) {
+                *slot = new;
+            }
+        }
+        
+        if let Some(index) = self.heads.remove(&old) {
+            self.chains[index].head = new;
+            self.heads.insert(new, index);
+        }
+        
+        // les entrées rangées dans le cluster déplacé l'ont suivi
+        let inside: Vec<usize> = self.entries
+            .range((old, 0)..=(old, usize::MAX))
+            .map(|&(_, index)| index)
+            .collect();
+        for index in inside {
+            if let ClusterOwner::Entry(location) = self.owners[index] {
+                self.locate(index, EntryLocation {
+                    cluster: new,
+                    sector: (location.sector as i64 + offset) as u32,
+                    index: location.index,
+                });
+            }
+        }
+    }
+    
+    #[cfg(not(feature = "alloc"))]
+    fn moved(&mut self, _old: u32, _new: u32, _next: u32, _offset: i64) {}
+}
+
+impl<D: BlockDevice> Fat32Parser<'_, D> {
+    /// trouve le cluster dont l'entrée FAT pointe vers `cluster`
+    /// 
+    /// retourne `None` si `cluster` est le début d'une chaîne.
+    pub fn find_predecessor(&self, cluster: u32) -> Result<Option<u32>, Fat32Error<D::Error>> {
+        for candidate in 2..=self.max_cluster() {
+            if self.read_fat_entry(candidate)? == cluster {
+                return Ok(Some(candidate));
+            }
+        }
+        
+        Ok(None)
+    }
+    
+    /// trouve ce qui désigne `cluster` : son prédécesseur, l'entrée dont
+    /// il est le premier cluster, ou la racine
+    /// 
+    /// lit toute la FAT et parcourt l'arborescence ; `ClusterReferences`
+    /// évite de recommencer pour chaque cluster.
+    pub fn find_owner(&self, cluster: u32) -> Result<ClusterOwner, Fat32Error<D::Error>> {
+        if let Some(previous) = self.find_predecessor(cluster)? {
+            return Ok(ClusterOwner::Predecessor(previous));
+        }
+        if self.boot_sector.root_cluster == cluster {
+            return Ok(ClusterOwner::Root);
+        }
+        
+        let mut owner = ClusterOwner::Lost;
+        self.walk_tree(&mut |entry, location, _depth| {
+            if entry.first_cluster() == cluster {
+                owner = ClusterOwner::Entry(location);
+                return Ok(Visit::Stop);
+            }
+            Ok(Visit::Continue)
+        })?;
+        
+        Ok(owner)
+    }
+    
+    /// construit la table inverse des références aux clusters
+    /// 
+    /// les chaînes sont rangées dans l'ordre de `walk_tree`, racine en
+    /// tête.
+    #[cfg(feature = "alloc")]
+    pub fn cluster_references(&self) -> Result<ClusterReferences, Fat32Error<D::Error>> {
+        let max_cluster = self.max_cluster();
+        let mut references = ClusterReferences {
+            predecessors: alloc::vec![0; max_cluster as usize + 1],
+            ..ClusterReferences::default()
+        };
+        
+        self.scan_fat(|cluster, next| {
+            if (2..=max_cluster).contains(&next) {
+                references.predecessors[next as usize] = cluster;
+            }
+        })?;
+        
+        let root = self.boot_sector.root_cluster;
+        references.chains.push(Chain {
+            head: root,
+            is_directory: true,
+        });
+        references.owners.push(ClusterOwner::Root);
+        references.heads.insert(root, 0);
+        
+        self.walk_tree(&mut |entry, location, _depth| {
+            let head = entry.first_cluster();
+            if head >= 2 {
+                let index = references.chains.len();
+                references.chains.push(Chain {
+                    head,
+                    is_directory: entry.is_directory(),
+                });
+                references.owners.push(ClusterOwner::Entry(location));
+                references.heads.entry(head).or_insert(index);
+                references.entries.insert((location.cluster, index));
+            }
+            Ok(Visit::Continue)
+        })?;
+        
+        Ok(references)
+    }
+    
+    /// table vide : les références sont cherchées à chaque déplacement
+    #[cfg(not(feature = "alloc"))]
+    pub fn cluster_references(&self) -> Result<ClusterReferences, Fat32Error<D::Error>> {
+        Ok(ClusterReferences::default())
+    }
+    
+    /// retourne la chaîne d'indice `index` dans l'ordre de `walk_tree`,
+    /// racine en tête
+    #[cfg(feature = "alloc")]
+    pub(crate) fn chain_at(&self, references: &ClusterReferences, index: usize) -> Result<Option<Chain>, Fat32Error<D::Error>> {
+        Ok(references.chains.get(index).copied())
+    }
+    
+    /// retourne la chaîne d'indice `index` dans l'ordre de `walk_tree`,
+    /// racine en tête
+    /// 
+    /// sans table, l'arborescence est parcourue jusqu'à cette chaîne.
+    #[cfg(not(feature = "alloc"))]
+    pub(crate) fn chain_at(&self, _references: &ClusterReferences, index: usize) -> Result<Option<Chain>, Fat32Error<D::Error>> {
+        if index == 0 {
+            return Ok(Some(Chain {
+                head: self.boot_sector.root_cluster,
+                is_directory: true,
+            }));
+        }
+        
+        let mut remaining = index;
+        let mut found = None;
+        self.walk_tree(&mut |entry, _location, _depth| {
+            if entry.first_cluster() < 2 {
+                return Ok(Visit::Continue);
+            }
+            remaining -= 1;
+            if remaining == 0 {
+                found = Some(Chain {
+                    head: entry.first_cluster(),
+                    is_directory: entry.is_directory(),
+                });
+                return Ok(Visit::Stop);
+            }
+            Ok(Visit::Continue)
+        })?;
+        
+        Ok(found)
+    }
+    
+    /// met à jour la table après une réorganisation des entrées d'un
+    /// répertoire
+    #[cfg(feature = "alloc")]
+    pub(crate) fn refresh_references(&self, dir_cluster: u32, references: &mut ClusterReferences) -> Result<(), Fat32Error<D::Error>> {
+        self.walk_dir(dir_cluster, &mut |entry, location| {
+            if entry.is_empty() || entry.is_long_name() || entry.is_dot() || entry.is_dotdot() {
+                return Ok(Visit::Continue);
+            }
+            if let Some(&index) = references.heads.get(&entry.first_cluster()) {
+                references.locate(index, location);
+            }
+            Ok(Visit::Continue)
+        })?;
+        Ok(())
+    }
+    
+    #[cfg(not(feature = "alloc"))]
+    pub(crate) fn refresh_references(&self, _dir_cluster: u32, _references: &mut ClusterReferences) -> Result<(), Fat32Error<D::Error>> {
+        Ok(())
+    }
+    
+    fn find_first_cluster_reference(&self, cluster: u32) -> Result<Option<EntryLocation>, Fat32Error<D::Error>> {
+        let mut found = None;
+        let root = self.boot_sector.root_cluster;
+        
+        // les entrées "." et ".." ne sont pas visitées par walk_tree
+        let check = |dir_cluster: u32, found: &mut Option<EntryLocation>| {
+            self.walk_dir(dir_cluster, &mut |entry, location| {
+                if (entry.is_dot() || entry.is_dotdot()) && entry.first_cluster() == cluster {
+                    *found = Some(location);
+                    return Ok(Visit::Stop);
+                }
+                Ok(Visit::Continue)
+            })
+        };
+        
+        if check(root, &mut found)? == Visit::Stop {
+            return Ok(found);
+        }
+        
+        self.walk_tree(&mut |entry, location, _depth| {
+            if entry.first_cluster() == cluster {
+                found = Some(location);
+                return Ok(Visit::Stop);
+            }
+            if entry.is_directory() && entry.first_cluster() >= 2 {
+                return check(entry.first_cluster(), &mut found);
+            }
+            Ok(Visit::Continue)
+        })?;
+        
+        Ok(found)
+    }
+    
+    /// vérifie que `owner` désigne bien `cluster`
+    fn check_owner(&self, cluster: u32, owner: ClusterOwner) -> Result<(), Fat32Error<D::Error>> {
+        let matches = match owner {
+            ClusterOwner::Predecessor(previous) => self.read_fat_entry(previous)? == cluster,
+            ClusterOwner::Entry(location) => self.read_dir_entry(location)?.first_cluster() == cluster,
+            ClusterOwner::Root => self.boot_sector.root_cluster == cluster,
+            ClusterOwner::Lost => true,
+        };
+        
+        match (matches, owner) {
+            (true, _) => Ok(()),
+            (false, ClusterOwner::Entry(location)) => Err(Fat32Error::InvalidEntry {
+                sector: location.sector as u64,
+                index: location.index,
+            }),
+            (false, _) => Err(Fat32Error::BadChain { cluster }),
+        }
+    }
+}
+
+impl<D: BlockDeviceMut> Fat32Parser<'_, D> {
+    /// remplace toutes les références à `old` comme premier cluster
+    /// 
+    /// met à jour les entrées de répertoire actives (y compris "." et
+    /// "..") ainsi que le cluster racine du boot sector. rien à faire si
+    /// `old` et `new` sont égaux.
+    pub fn replace_first_cluster(&mut self, old: u32, new: u32) -> Result<(), Fat32Error<D::Error>> {
+        if old == new {
+            return Ok(());
+        }
+        
+        if self.boot_sector.root_cluster == old {
+            self.boot_sector.root_cluster = new;
+            self.write_boot_sector()?;
+        }
+        
+        // une référence à la fois : l'entrée modifiée ne correspond plus
+        while let Some(location) = self.find_first_cluster_reference(old)? {
+            let mut entry = self.read_dir_entry(location)?;
+            entry.set_first_cluster(new);
+            self.write_dir_entry(location, &entry)?;
+        }
+        
+        Ok(())
+    }
+    
+    /// copie le contenu d'un cluster vers un autre, par lots de secteurs
+    pub fn copy_cluster(&mut self, from: u32, to: u32) -> Result<(), Fat32Error<D::Error>> {
+        let source = self.cluster_sector(from)?;
+        let target = self.cluster_sector(to)?;
+        let sectors = self.boot_sector.sectors_per_cluster as u32;
+        let mut buffer = [0u8; 512 * COPY_BATCH];
+        self.mark_dirty()?;
+        
+        let mut done = 0;
+        while done < sectors {
+            let count = core::cmp::min(COPY_BATCH as u32, sectors - done);
+            let chunk = &mut buffer[..count as usize * 512];
+            self.read_sectors(source + done, chunk)?;
+            self.write_sectors(target + done, chunk)?;
+            done += count;
+        }
+        
+        Ok(())
+    }
+    
+    /// déplace un cluster alloué vers un cluster libre
+    /// 
+    /// les données sont copiées avant la mise à jour des pointeurs, puis
+    /// l'ancien cluster est libéré. la référence du cluster est cherchée
+    /// dans toute la FAT et l'arborescence (voir `relocate_cluster_from`).
+    pub fn relocate_cluster(&mut self, old: u32, new: u32) -> Result<(), Fat32Error<D::Error>> {
+        self.check_relocation(old, new)?;
+        let owner = self.find_owner(old)?;
+        self.relocate_cluster_from(old, new, owner)
+    }
+    
+    /// déplace un cluster alloué vers un cluster libre, en mettant à jour
+    /// la référence `owner` déjà connue
+    /// 
+    /// pour le premier cluster d'un répertoire, son entrée "." et les
+    /// entrées ".." de ses sous-répertoires sont aussi mises à jour.
+    pub fn relocate_cluster_from(&mut self, old: u32, new: u32, owner: ClusterOwner) -> Result<(), Fat32Error<D::Error>> {
+        let next = self.check_relocation(old, new)?;
+        self.check_owner(old, owner)?;
+        
+        self.copy_cluster(old, new)?;
+        self.write_fat_entry(new, next)?;
+        
+        match owner {
+            ClusterOwner::Predecessor(previous) => self.write_fat_entry(previous, new)?,
+            ClusterOwner::Entry(location) => {
+                let mut entry = self.read_dir_entry(location)?;
+                entry.set_first_cluster(new);
+                self.write_dir_entry(location, &entry)?;
+                if entry.is_directory() {
+                    self.replace_dot_references(new, old)?;
+                }
+            }
+            ClusterOwner::Root => {
+                self.boot_sector.root_cluster = new;
+                self.write_boot_sector()?;
+                self.replace_dot_references(new, old)?;
+            }
+            ClusterOwner::Lost => {}
+        }
+        
+        self.free_cluster(old)
+    }
+    
+    /// déplace un cluster en tenant `references` à jour
+    /// 
+    /// la référence passée dans `owner` est prioritaire ; à défaut, elle
+    /// est prise dans la table, ou cherchée si la table l'ignore.
+    pub(crate) fn relocate_tracked(
+        &mut self,
+        old: u32,
+        new: u32,
+        owner: Option<ClusterOwner>,
+        references: &mut ClusterReferences,
+    ) -> Result<(), Fat32Error<D::Error>> {
+        let next = self.check_relocation(old, new)?;
+        let owner = match owner.or_else(|| references.owner(old)) {
+            Some(owner) => owner,
+            None => self.find_owner(old)?,
+        };
+        let offset = self.cluster_sector(new)? as i64 - self.cluster_sector(old)? as i64;
+        
+        self.relocate_cluster_from(old, new, owner)?;
+        references.moved(old, new, next, offset);
+        Ok(())
+    }
+    
+    /// vérifie qu'un déplacement est possible et retourne le cluster qui
+    /// suit `old`
+    fn check_relocation(&self, old: u32, new: u32) -> Result<u32, Fat32Error<D::Error>> {
+        let max_cluster = self.max_cluster();
+        for cluster in [old, new] {
+            if cluster < 2 || cluster > max_cluster {
+                return Err(Fat32Error::InvalidCluster { cluster });
+            }
+        }
+        
+        let next = self.read_fat_entry(old)?;
+        if fat::is_free(next) {
+            return Err(Fat32Error::InvalidCluster { cluster: old });
+        }
+        if !fat::is_free(self.read_fat_entry(new)?) {
+            return Err(Fat32Error::InvalidCluster { cluster: new });
+        }
+        
+        Ok(next)
+    }
+    
+    /// met à jour les entrées "." et ".." qui désignent un répertoire
+    /// déplacé
+    /// 
+    /// `dir_cluster` est le nouveau premier cluster du répertoire : son
+    /// entrée "." et l'entrée ".." de chaque sous-répertoire valent encore
+    /// `old`. chaque secteur est lu une fois.
+    fn replace_dot_references(&mut self, dir_cluster: u32, old: u32) -> Result<(), Fat32Error<D::Error>> {
+        let sectors_per_cluster = self.boot_sector.sectors_per_cluster as u32;
+        let max_cluster = self.max_cluster();
+        let mut current = dir_cluster;
+        let mut visited = 0;
+        let mut buffer = [0u8; 512];
+        
+        while !fat::is_eoc(current) {
+            if current < 2 || current > max_cluster || visited > max_cluster {
+                return Err(Fat32Error::BadChain { cluster: current });
+            }
+            
+            let first_sector = self.cluster_sector(current)?;
+            for sector in first_sector..first_sector + sectors_per_cluster {
+                self.read_sector(sector, &mut buffer)?;
+                
+                for index in 0..ENTRIES_PER_SECTOR {
+                    let offset = index * 32;
+                    let mut entry = DirEntry::parse(&buffer[offset..offset + 32]).map_err(Fat32Error::widen)?;
+                    if entry.name[0] == ENTRY_EMPTY {
+                        return Ok(());
+                    }
+                    
+                    if entry.is_dot() && entry.first_cluster() == old {
+                        entry.set_first_cluster(dir_cluster);
+                        self.write_dir_entry(EntryLocation { cluster: current, sector, index }, &entry)?;
+                    } else if entry.is_directory() && !entry.is_empty() && !entry.is_long_name()
+                        && !entry.is_dotdot() && entry.first_cluster() >= 2
+                    {
+                        self.replace_parent_reference(entry.first_cluster(), old, dir_cluster)?;
+                    }
+                }
+            }
+            
+            visited += 1;
+            current = self.read_fat_entry(current)?;
+        }
+        
+        Ok(())
+    }
+    
+    /// remplace `old` par `new` dans l'entrée ".." d'un sous-répertoire
+    fn replace_parent_reference(&mut self, dir_cluster: u32, old: u32, new: u32) -> Result<(), Fat32Error<D::Error>> {
+        let sector = self.cluster_sector(dir_cluster)?;
+        let mut buffer = [0u8; 512];
+        self.read_sector(sector, &mut buffer)?;
+        
+        for index in 0..ENTRIES_PER_SECTOR {
+            let offset = index * 32;
+            let mut entry = DirEntry::parse(&buffer[offset..offset + 32]).map_err(Fat32Error::widen)?;
+            if entry.is_dotdot() && entry.first_cluster() == old {
+                entry.set_first_cluster(new);
+                return self.write_dir_entry(EntryLocation { cluster: dir_cluster, sector, index }, &entry);
+            }
+        }
+        
+        Ok(())
+    }
+}
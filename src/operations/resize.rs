@@ -0,0 +1,196 @@
+//! redimensionnement d'un volume FAT32
+//! 
+//! agrandit ou réduit un volume existant. à l'agrandissement, la FAT est
+//! étendue si nécessaire (la zone de données est alors décalée) ; à la
+//! réduction, les clusters situés au-delà de la nouvelle fin sont
+//! d'abord déplacés vers des clusters libres.
+
+use crate::operations::parser::Fat32Parser;
+use crate::operations::relocate::COPY_BATCH;
+use crate::structures::boot_sector::BootSector;
+use crate::traits::block_device::{BlockDeviceMut, SectorKind};
+use crate::utils::error::Fat32Error;
+use crate::utils::fat;
+
+impl<D: BlockDeviceMut> Fat32Parser<'_, D> {
+    /// redimensionne le volume à `new_total_sectors` secteurs
+    /// 
+    /// retourne `DiskFull` si les données ne tiennent pas dans le volume
+    /// réduit, et `InvalidSector` si la taille demandée est trop petite
+    /// ou dépasse la capacité du dispositif. un redimensionnement refusé
+    /// ne modifie pas le volume, pas même son bit de démontage propre.
+    pub fn resize(&mut self, new_total_sectors: u32) -> Result<(), Fat32Error<D::Error>> {
+        let old_total = self.boot_sector.total_sectors();
+        if new_total_sectors > old_total {
+            self.grow(new_total_sectors)
+        } else if new_total_sectors < old_total {
+            self.shrink(new_total_sectors)
+        } else {
+            Ok(())
+        }
+    }
+    
+    /// retourne le boot sector du volume redimensionné à `total_sectors`
+    fn resized_boot_sector(&self, total_sectors: u32) -> BootSector {
+        let mut resized = self.boot_sector;
+        resized.total_sectors_32 = total_sectors;
+        resized.total_sectors_16 = 0;
+        resized
+    }
+    
+    /// calcule la taille de FAT nécessaire pour un nombre total de secteurs
+    fn required_fat_size(&self, total_sectors: u32) -> Result<u32, Fat32Error<D::Error>> {
+        let mut resized = self.resized_boot_sector(total_sectors);
+        
+        loop {
+            let geometry = resized.geometry();
+            if geometry.cluster_count == 0 {
+                return Err(Fat32Error::InvalidSector { sector: total_sectors as u64 });
+            }
+            
+            let needed = geometry.fat_sectors_needed();
+            if needed <= geometry.fat_size {
+                return Ok(geometry.fat_size);
+            }
+            resized.fat_size_32 = needed;
+        }
+    }
+    
+    // volume marqué « sale » seulement une fois la taille validée
+    fn grow(&mut self, new_total: u32) -> Result<(), Fat32Error<D::Error>> {
+        // vérifier que le dispositif contient le nouveau dernier secteur
+        let mut buffer = [0u8; 512];
+        self.read_sector(new_total - 1, &mut buffer)?;
+        
+        let old_fat_size = self.boot_sector.fat_size();
+        let new_fat_size = self.required_fat_size(new_total)?;
+        self.mark_dirty()?;
+        
+        if new_fat_size > old_fat_size {
+            self.move_data_region(old_fat_size, new_fat_size)?;
+            self.rewrite_fats(old_fat_size, new_fat_size)?;
+            self.boot_sector.fat_size_32 = new_fat_size;
+        }
+        
+        self.boot_sector.total_sectors_32 = new_total;
+        self.boot_sector.total_sectors_16 = 0;
+        self.geometry = self.boot_sector.geometry();
+        self.write_boot_sector()?;
+        self.rebuild_free_bitmap()?;
+        self.invalidate_free_count()
+    }
+    
+    /// décale la zone de données pour laisser la place à une FAT plus grande
+    /// 
+    /// la copie se fait en partant de la fin car la zone se déplace vers
+    /// les secteurs hauts.
+    fn move_data_region(&mut self, old_fat_size: u32, new_fat_size: u32) -> Result<(), Fat32Error<D::Error>> {
+        let old_start = self.geometry.data_start;
+        let new_start = old_start + self.geometry.num_fats * (new_fat_size - old_fat_size);
+        
+        let highest = match self.highest_used_cluster()? {
+            Some(cluster) => cluster,
+            None => return Ok(()),
+        };
+        let used_sectors = (highest - 1) * self.geometry.sectors_per_cluster;
+        
+        // chaque lot est lu en entier avant d'être écrit plus haut
+        let mut buffer = [0u8; 512 * COPY_BATCH];
+        let mut end = used_sectors;
+        while end > 0 {
+            let count = core::cmp::min(COPY_BATCH as u32, end);
+            let chunk = &mut buffer[..count as usize * 512];
+            self.read_sectors(old_start + end - count, chunk)?;
+            self.write_sectors(new_start + end - count, chunk)?;
+            end -= count;
+        }
+        
+        Ok(())
+    }
+    
+    /// réécrit toutes les copies de la FAT à partir de la première
+    fn rewrite_fats(&mut self, old_fat_size: u32, new_fat_size: u32) -> Result<(), Fat32Error<D::Error>> {
+        let fat_start = self.geometry.fat_start;
+        let num_fats = self.geometry.num_fats;
+        let mut buffer = [0u8; 512];
+        
+        for s in 0..new_fat_size {
+            if s < old_fat_size {
+                self.read_sector(fat_start + s, &mut buffer)?;
+            } else {
+                buffer = [0u8; 512];
+            }
+            
+            for copy in 0..num_fats {
+                self.write_sector_kind(fat_start + copy * new_fat_size + s, &buffer, SectorKind::Fat)?;
+            }
+        }
+        
+        Ok(())
+    }
+    
+    fn shrink(&mut self, new_total: u32) -> Result<(), Fat32Error<D::Error>> {
+        let geometry = self.resized_boot_sector(new_total).geometry();
+        if geometry.cluster_count == 0 {
+            return Err(Fat32Error::InvalidSector { sector: new_total as u64 });
+        }
+        
+        let old_max = self.max_cluster();
+        let new_max = geometry.max_cluster();
+        
+        // vérifier que les clusters à déplacer tiennent dans l'espace libre
+        let mut free_below = 0;
+        for cluster in 2..=new_max {
+            if fat::is_free(self.read_fat_entry(cluster)?) {
+                free_below += 1;
+            }
+        }
+        
+        let mut used_above = 0;
+        for cluster in new_max + 1..=old_max {
+            let entry = self.read_fat_entry(cluster)?;
+            if !fat::is_free(entry) && !fat::is_bad(entry) {
+                used_above += 1;
+            }
+        }
+        
+        if used_above > free_below {
+            return Err(Fat32Error::DiskFull);
+        }
+        
+        // une seule table des références pour tous les déplacements
+        let mut references = self.cluster_references()?;
+        self.mark_dirty()?;
+        let mut target = 2;
+        for cluster in new_max + 1..=old_max {
+            let entry = self.read_fat_entry(cluster)?;
+            if fat::is_free(entry) || fat::is_bad(entry) {
+                continue;
+            }
+            
+            while !fat::is_free(self.read_fat_entry(target)?) {
+                target += 1;
+            }
+            self.relocate_tracked(cluster, target, None, &mut references)?;
+        }
+        
+        // le cluster racine a pu être déplacé : seule la taille change
+        self.boot_sector.total_sectors_32 = new_total;
+        self.boot_sector.total_sectors_16 = 0;
+        self.geometry = geometry;
+        self.write_boot_sector()?;
+        self.rebuild_free_bitmap()?;
+        self.invalidate_free_count()
+    }
+    
+    /// retourne le plus grand cluster alloué
+    fn highest_used_cluster(&self) -> Result<Option<u32>, Fat32Error<D::Error>> {
+        for cluster in (2..=self.max_cluster()).rev() {
+            if !fat::is_free(self.read_fat_entry(cluster)?) {
+                return Ok(Some(cluster));
+            }
+        }
+        
+        Ok(None)
+    }
+}
@@ -0,0 +1,138 @@
+//! extraction du slack et des emplacements inutilisés
+//! 
+//! le slack d'un fichier est la partie de son dernier cluster située
+//! après `file_size` : elle contient souvent des restes d'anciennes
+//! données. les emplacements de répertoire supprimés (ou situés après
+//! le marqueur de fin) conservent eux aussi d'anciennes entrées.
+
+use crate::operations::directory::{EntryLocation, Visit};
+use crate::operations::parser::Fat32Parser;
+use crate::structures::dir_entry::DirEntry;
+use crate::traits::block_device::BlockDevice;
+use crate::utils::constants::{ENTRY_DELETED, ENTRY_EMPTY};
+use crate::utils::error::Fat32Error;
+
+/// octets de slack d'un fichier, secteur par secteur
+#[derive(Debug, Clone, Copy)]
+pub struct SlackRegion<'a> {
+    /// entrée du fichier
+    pub entry: &'a DirEntry,
+    /// position de l'entrée du fichier
+    pub entry_location: EntryLocation,
+    /// dernier cluster du fichier
+    pub cluster: u32,
+    /// secteur contenant les octets
+    pub sector: u32,
+    /// position des octets dans le secteur
+    pub offset: usize,
+    /// octets situés après la fin du fichier
+    pub data: &'a [u8],
+}
+
+/// emplacement de répertoire inutilisé contenant encore des données
+#[derive(Debug, Clone, Copy)]
+pub struct UnusedSlot {
+    /// position de l'emplacement
+    pub location: EntryLocation,
+    /// contenu brut de l'emplacement
+    pub raw: [u8; 32],
+    /// `true` si l'emplacement suit le marqueur de fin du répertoire
+    pub after_end: bool,
+}
+
+impl<D: BlockDevice> Fat32Parser<'_, D> {
+    /// parcourt le slack de tous les fichiers alloués
+    /// 
+    /// le callback reçoit une région par secteur ; les fichiers dont la
+    /// taille est un multiple de la taille de cluster n'ont pas de slack.
+    /// sur une image tronquée, les fichiers dont le dernier cluster est
+    /// absent sont ignorés.
+    pub fn for_each_file_slack<F>(&self, mut f: F) -> Result<(), Fat32Error<D::Error>>
+    where
+        F: FnMut(&SlackRegion) -> Result<Visit, Fat32Error<D::Error>>,
+    {
+        let cluster_size = self.boot_sector.cluster_size();
+        let mut buffer = [0u8; 512];
+        
+        self.walk_tree(&mut |entry, location, _depth| {
+            let size = entry.file_size;
+            if !entry.is_file() || size == 0 || entry.first_cluster() < 2 || size % cluster_size == 0 {
+                return Ok(Visit::Continue);
+            }
+            
+            let cluster = self.chain_cluster_at(entry.first_cluster(), (size - 1) / cluster_size)?;
+            if !self.is_cluster_readable(cluster) {
+                return Ok(Visit::Continue);
+            }
+            let first_sector = self.cluster_sector(cluster)?;
+            let slack_start = (size % cluster_size) as usize;
+            
+            for s in slack_start / 512..self.boot_sector.sectors_per_cluster as usize {
+                let sector = first_sector + s as u32;
+                self.read_sector(sector, &mut buffer)?;
+                
+                let offset = if s == slack_start / 512 { slack_start % 512 } else { 0 };
+                let region = SlackRegion {
+                    entry,
+                    entry_location: location,
+                    cluster,
+                    sector,
+                    offset,
+                    data: &buffer[offset..],
+                };
+                if f(&region)? == Visit::Stop {
+                    return Ok(Visit::Stop);
+                }
+            }
+            
+            Ok(Visit::Continue)
+        })?;
+        
+        Ok(())
+    }
+    
+    /// parcourt les emplacements de répertoire supprimés et ceux qui,
+    /// après le marqueur de fin, contiennent encore des données
+    /// 
+    /// comme `walk_tree`, ignore les répertoires coupés par la fin d'une
+    /// image tronquée.
+    pub fn for_each_unused_dir_slot<F>(&self, mut f: F) -> Result<(), Fat32Error<D::Error>>
+    where
+        F: FnMut(&UnusedSlot) -> Result<Visit, Fat32Error<D::Error>>,
+    {
+        if self.unused_slots_in(self.boot_sector.root_cluster, &mut f)? == Visit::Stop {
+            return Ok(());
+        }
+        
+        self.walk_tree(&mut |entry, _location, _depth| {
+            let cluster = entry.first_cluster();
+            if entry.is_directory() && cluster >= 2 && self.first_missing_cluster(cluster, u32::MAX)?.is_none() {
+                return self.unused_slots_in(cluster, &mut f);
+            }
+            Ok(Visit::Continue)
+        })?;
+        
+        Ok(())
+    }
+    
+    fn unused_slots_in<F>(&self, dir_cluster: u32, f: &mut F) -> Result<Visit, Fat32Error<D::Error>>
+    where
+        F: FnMut(&UnusedSlot) -> Result<Visit, Fat32Error<D::Error>>,
+    {
+        let mut after_end = false;
+        
+        self.walk_dir_slots(dir_cluster, &mut |entry, location| {
+            let raw = entry.to_bytes();
+            if entry.name[0] == ENTRY_EMPTY {
+                after_end = true;
+            }
+            
+            let unused = if after_end { raw.iter().any(|&b| b != 0) } else { entry.name[0] == ENTRY_DELETED };
+            if !unused {
+                return Ok(Visit::Continue);
+            }
+            
+            f(&UnusedSlot { location, raw, after_end })
+        })
+    }
+}
@@ -0,0 +1,522 @@
+//! étapes communes aux parsers synchrone et asynchrone
+//! 
+//! ces automates ne font aucune entrée/sortie : ils décident de la
+//! prochaine lecture ou écriture, et reçoivent son résultat. `Fat32Parser`
+//! et `AsyncFat32Parser` les pilotent chacun avec leur dispositif, si bien
+//! que le suivi des chaînes, la lecture par suites contiguës, le
+//! découpage des transferts, l'allocation, l'état du volume et le
+//! décodage des secteurs ne sont écrits qu'une fois.
+
+use core::ops::Range;
+use crate::structures::boot_sector::BootSector;
+use crate::structures::dir_entry::DirEntry;
+use crate::operations::volume_state::{VolumeFlags, CLEAN_SHUTDOWN_BIT};
+use crate::structures::geometry::Geometry;
+use crate::utils::error::Fat32Error;
+use crate::utils::fat;
+use crate::utils::validator::{MountOptions, ValidationReport};
+
+/// état du volume décodé au montage
+pub(crate) struct Mount {
+    pub boot_sector: BootSector,
+    pub validation: ValidationReport,
+    pub device_sectors: Option<u64>,
+}
+
+/// décode et valide le boot sector lu au secteur `partition_start`
+pub(crate) fn mount<E>(
+    buffer: &[u8; 512],
+    partition_start: u64,
+    device_count: Option<u64>,
+    options: MountOptions,
+) -> Result<Mount, Fat32Error<E>> {
+    let boot_sector = BootSector::parse(buffer).map_err(Fat32Error::widen)?;
+    let options = options.with_device_size(device_count, partition_start);
+    let validation = options.check(&boot_sector, partition_start)?;
+    
+    Ok(Mount {
+        boot_sector,
+        validation,
+        device_sectors: options.device_sectors,
+    })
+}
+
+/// lit l'entrée FAT 1 dans le premier secteur d'une copie de la FAT
+pub(crate) fn fat1_entry(buffer: &[u8]) -> u32 {
+    u32::from_le_bytes([buffer[4], buffer[5], buffer[6], buffer[7]])
+}
+
+/// modifie l'entrée FAT 1 dans le premier secteur d'une copie de la FAT
+pub(crate) fn patch_fat1<F: Fn(u32) -> u32>(buffer: &mut [u8], f: F) {
+    let entry = f(fat1_entry(buffer));
+    buffer[4..8].copy_from_slice(&entry.to_le_bytes());
+}
+
+/// secteurs qui portent l'entrée FAT 1 : le premier de chaque copie
+pub(crate) fn fat1_sectors(geometry: Geometry) -> impl Iterator<Item = u32> {
+    (0..geometry.num_fats).map(move |copy| geometry.fat_copy_start(copy))
+}
+
+/// modification de l'entrée FAT 1 avant la première écriture : retire le
+/// bit de démontage propre, `None` si le volume est déjà marqué « sale »
+pub(crate) fn dirty_patch(dirty: bool) -> Option<fn(u32) -> u32> {
+    (!dirty).then_some(|entry| entry & !CLEAN_SHUTDOWN_BIT)
+}
+
+/// modification de l'entrée FAT 1 au flush : remet le bit de démontage
+/// propre si le volume a été modifié
+/// 
+/// un volume déjà « sale » au montage le reste jusqu'à son contrôle.
+pub(crate) fn clean_patch(dirty: bool, flags: VolumeFlags) -> Option<fn(u32) -> u32> {
+    (dirty && flags.clean_shutdown).then_some(|entry| entry | CLEAN_SHUTDOWN_BIT)
+}
+
+/// décode l'entrée `index` d'un secteur de répertoire
+pub(crate) fn entry_at<E>(buffer: &[u8], index: usize) -> Result<DirEntry, Fat32Error<E>> {
+    let offset = index * 32;
+    DirEntry::parse(&buffer[offset..offset + 32]).map_err(Fat32Error::widen)
+}
+
+/// encode `entry` à la place `index` d'un secteur de répertoire
+pub(crate) fn set_entry_at<E>(buffer: &mut [u8], index: usize, entry: &DirEntry) -> Result<(), Fat32Error<E>> {
+    let offset = index * 32;
+    entry.write_to(&mut buffer[offset..offset + 32]).map_err(Fat32Error::widen)
+}
+
+/// premier secteur d'une suite de `count` clusters, qui doit tenir
+/// entièrement dans la zone de données
+pub(crate) fn run_sector<E>(geometry: &Geometry, first_cluster: u32, count: u32) -> Result<u32, Fat32Error<E>> {
+    let last = first_cluster.saturating_add(count.saturating_sub(1));
+    geometry.cluster_to_sector(last).ok_or(Fat32Error::InvalidCluster { cluster: last })?;
+    geometry.cluster_to_sector(first_cluster).ok_or(Fat32Error::InvalidCluster { cluster: first_cluster })
+}
+
+/// premier secteur et taille de la lecture de `count` clusters contigus
+/// dans un tampon de `len` octets
+pub(crate) fn read_run_transfer<E>(geometry: &Geometry, first_cluster: u32, count: u32, len: usize) -> Result<(u32, usize), Fat32Error<E>> {
+    let first_sector = run_sector(geometry, first_cluster, count)?;
+    let bytes = (count as usize)
+        .checked_mul(geometry.sectors_per_cluster as usize * 512)
+        .filter(|&bytes| bytes <= len)
+        .ok_or(Fat32Error::BufferTooSmall)?;
+    Ok((first_sector, bytes))
+}
+
+/// premier secteur et taille de l'écriture de `len` octets à partir de
+/// `first_cluster` : seuls les secteurs complets sont écrits
+pub(crate) fn write_run_transfer<E>(geometry: &Geometry, first_cluster: u32, len: usize) -> Result<(u32, usize), Fat32Error<E>> {
+    let whole = len / 512 * 512;
+    let clusters = u32::try_from(whole.div_ceil(geometry.sectors_per_cluster as usize * 512))
+        .map_err(|_| Fat32Error::BufferTooSmall)?;
+    let first_sector = run_sector(geometry, first_cluster, clusters)?;
+    Ok((first_sector, whole))
+}
+
+/// transfert pour le début d'un cluster
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum HeadPart {
+    /// secteurs complets, d'un seul transfert, depuis ou vers cette
+    /// partie du tampon
+    Sectors { sector: u32, range: Range<usize> },
+    /// secteur partiel, par un tampon d'un secteur complété par des zéros ;
+    /// `range` est vide pour un secteur de remplissage
+    Partial { sector: u32, range: Range<usize> },
+}
+
+/// découpage du début d'un cluster en transferts
+/// 
+/// les secteurs complets sont transférés directement, le dernier secteur
+/// partiel passe par un tampon d'un secteur : la taille des clusters n'est
+/// pas limitée par un tampon intermédiaire. à l'écriture, le reste du
+/// cluster est complété par des zéros.
+pub(crate) struct ClusterHead {
+    sector: u32,
+    end: u32,
+    len: usize,
+    offset: usize,
+}
+
+impl ClusterHead {
+    /// lecture des `len` premiers octets de `cluster`
+    pub(crate) fn read<E>(geometry: &Geometry, cluster: u32, len: usize) -> Result<Self, Fat32Error<E>> {
+        let sector = geometry.cluster_to_sector(cluster).ok_or(Fat32Error::InvalidCluster { cluster })?;
+        Ok(Self {
+            sector,
+            end: sector + len.div_ceil(512) as u32,
+            len,
+            offset: 0,
+        })
+    }
+    
+    /// écriture de `len` octets au début de `cluster`, puis de zéros
+    /// jusqu'à sa fin
+    pub(crate) fn write<E>(geometry: &Geometry, cluster: u32, len: usize) -> Result<Self, Fat32Error<E>> {
+        let sector = geometry.cluster_to_sector(cluster).ok_or(Fat32Error::InvalidCluster { cluster })?;
+        Ok(Self {
+            sector,
+            end: sector + geometry.sectors_per_cluster,
+            len,
+            offset: 0,
+        })
+    }
+}
+
+impl Iterator for ClusterHead {
+    type Item = HeadPart;
+    
+    fn next(&mut self) -> Option<HeadPart> {
+        let whole = self.len / 512 * 512;
+        if self.offset < whole {
+            let part = HeadPart::Sectors { sector: self.sector, range: 0..whole };
+            self.sector += (whole / 512) as u32;
+            self.offset = whole;
+            return Some(part);
+        }
+        if self.sector >= self.end {
+            return None;
+        }
+        
+        let part = HeadPart::Partial { sector: self.sector, range: self.offset..self.len };
+        self.sector += 1;
+        self.offset = self.len;
+        Some(part)
+    }
+}
+
+/// parcours d'une chaîne de clusters
+/// 
+/// chaque cluster est vérifié avant d'être rendu : hors de la zone de
+/// données, ou au-delà d'autant de clusters que le volume en compte (la
+/// chaîne boucle), le parcours s'arrête sur `BadChain`.
+pub(crate) struct ChainWalk {
+    current: u32,
+    max_cluster: u32,
+    visited: u32,
+    /// un cluster libre termine la chaîne, pour la libérer
+    until_free: bool,
+}
+
+impl ChainWalk {
+    pub(crate) fn new(head: u32, max_cluster: u32) -> Self {
+        Self {
+            current: head,
+            max_cluster,
+            visited: 0,
+            until_free: false,
+        }
+    }
+    
+    /// parcours d'une chaîne à libérer, qui s'arrête aussi sur un cluster
+    /// déjà libre
+    pub(crate) fn releasing(head: u32, max_cluster: u32) -> Self {
+        Self {
+            until_free: true,
+            ..Self::new(head, max_cluster)
+        }
+    }
+    
+    /// cluster courant, `None` en fin de chaîne
+    pub(crate) fn current<E>(&self) -> Result<Option<u32>, Fat32Error<E>> {
+        let current = self.current;
+        if fat::is_eoc(current) || (self.until_free && fat::is_free(current)) {
+            return Ok(None);
+        }
+        if current < 2 || current > self.max_cluster || self.visited > self.max_cluster {
+            return Err(Fat32Error::BadChain { cluster: current });
+        }
+        Ok(Some(current))
+    }
+    
+    /// passe au cluster suivant, `entry` étant l'entrée de FAT du cluster
+    /// courant
+    pub(crate) fn advance(&mut self, entry: u32) {
+        self.advance_run(entry, 1);
+    }
+    
+    /// passe `count` clusters consécutifs, `entry` étant l'entrée de FAT
+    /// du dernier
+    pub(crate) fn advance_run(&mut self, entry: u32, count: u32) {
+        self.current = entry;
+        self.visited = self.visited.saturating_add(count);
+    }
+}
+
+/// étape du parcours d'un répertoire
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum DirStep {
+    /// lire ce secteur du cluster et en visiter les entrées
+    Sector { cluster: u32, sector: u32 },
+    /// lire l'entrée de FAT de ce cluster et la rendre à l'étape suivante
+    Fat(u32),
+    /// fin de la chaîne du répertoire
+    Done,
+}
+
+/// parcours des secteurs d'un répertoire, cluster par cluster
+pub(crate) struct DirWalk {
+    geometry: Geometry,
+    walk: ChainWalk,
+    cluster: u32,
+    sectors: Range<u32>,
+    /// tous les secteurs du cluster courant ont été rendus
+    cluster_done: bool,
+}
+
+impl DirWalk {
+    pub(crate) fn new(head: u32, geometry: &Geometry) -> Self {
+        Self {
+            geometry: *geometry,
+            walk: ChainWalk::new(head, geometry.max_cluster()),
+            cluster: 0,
+            sectors: 0..0,
+            cluster_done: false,
+        }
+    }
+    
+    /// étape suivante ; `entry` est l'entrée de FAT demandée par
+    /// `DirStep::Fat`
+    pub(crate) fn step<E>(&mut self, entry: Option<u32>) -> Result<DirStep, Fat32Error<E>> {
+        if let Some(entry) = entry {
+            self.walk.advance(entry);
+        }
+        if let Some(sector) = self.sectors.next() {
+            return Ok(DirStep::Sector { cluster: self.cluster, sector });
+        }
+        if self.cluster_done {
+            self.cluster_done = false;
+            return Ok(DirStep::Fat(self.cluster));
+        }
+        
+        let Some(cluster) = self.walk.current()? else {
+            return Ok(DirStep::Done);
+        };
+        let first = self.geometry.cluster_to_sector(cluster).ok_or(Fat32Error::InvalidCluster { cluster })?;
+        self.cluster = cluster;
+        self.sectors = first + 1..first + self.geometry.sectors_per_cluster;
+        self.cluster_done = true;
+        Ok(DirStep::Sector { cluster, sector: first })
+    }
+}
+
+/// étape de la lecture d'un fichier
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum ReadStep {
+    /// lire l'entrée de FAT de ce cluster et la rendre à l'étape suivante
+    Fat(u32),
+    /// lire `count` clusters contigus dans cette partie du tampon
+    Run { first: u32, count: u32, range: Range<usize> },
+    /// lire le début du cluster dans cette partie du tampon
+    Head { cluster: u32, range: Range<usize> },
+    /// lecture terminée, avec le nombre d'octets lus
+    Done(usize),
+}
+
+/// lecture d'un fichier par suites de clusters contigus
+/// 
+/// les clusters complets et consécutifs dans la chaîne sont lus d'un seul
+/// transfert ; le dernier cluster partiel est lu à part.
+pub(crate) struct FileRead {
+    cluster_size: usize,
+    len: usize,
+    offset: usize,
+    walk: ChainWalk,
+    /// premier cluster et longueur de la suite en cours de mesure
+    run: Option<(u32, u32)>,
+    /// nombre de clusters complets que le tampon peut encore recevoir
+    wanted: u32,
+}
+
+impl FileRead {
+    /// lecture de la chaîne `head` dans un tampon de `len` octets
+    /// 
+    /// un fichier vide (cluster 0) donne 0 octet.
+    pub(crate) fn new(head: u32, len: usize, cluster_size: u32, max_cluster: u32) -> Self {
+        let head = if head == 0 { fat::FAT_EOC } else { head };
+        Self {
+            cluster_size: cluster_size as usize,
+            len,
+            offset: 0,
+            walk: ChainWalk::new(head, max_cluster),
+            run: None,
+            wanted: 0,
+        }
+    }
+    
+    /// étape suivante ; `entry` est l'entrée de FAT demandée par
+    /// `ReadStep::Fat`
+    pub(crate) fn step<E>(&mut self, entry: Option<u32>) -> Result<ReadStep, Fat32Error<E>> {
+        if let (Some((first, count)), Some(next)) = (self.run, entry) {
+            // la suite se prolonge tant que la chaîne reste consécutive
+            if count < self.wanted && next == first + count {
+                self.run = Some((first, count + 1));
+                return Ok(ReadStep::Fat(next));
+            }
+            
+            let range = self.offset..self.offset + count as usize * self.cluster_size;
+            self.offset = range.end;
+            self.run = None;
+            self.walk.advance_run(next, count);
+            return Ok(ReadStep::Run { first, count, range });
+        }
+        
+        if self.offset >= self.len {
+            return Ok(ReadStep::Done(self.offset));
+        }
+        let Some(cluster) = self.walk.current()? else {
+            return Ok(ReadStep::Done(self.offset));
+        };
+        
+        self.wanted = ((self.len - self.offset) / self.cluster_size) as u32;
+        if self.wanted == 0 {
+            let range = self.offset..self.len;
+            self.offset = self.len;
+            return Ok(ReadStep::Head { cluster, range });
+        }
+        
+        self.run = Some((cluster, 1));
+        Ok(ReadStep::Fat(cluster))
+    }
+}
+
+/// étape de l'écriture d'un fichier
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum WriteStep {
+    /// écrire un cluster complet depuis cette partie des données
+    Cluster { cluster: u32, range: Range<usize> },
+    /// écrire le début du cluster et compléter par des zéros
+    Head { cluster: u32, range: Range<usize> },
+    /// lire l'entrée de FAT de ce cluster et la rendre à l'étape suivante
+    Fat(u32),
+    /// allouer un cluster après celui-ci et le rendre à l'étape suivante
+    Allocate(u32),
+    /// écriture terminée
+    Done,
+}
+
+/// écriture d'un fichier, en prolongeant sa chaîne si nécessaire
+pub(crate) struct FileWrite {
+    cluster_size: usize,
+    len: usize,
+    offset: usize,
+    current: u32,
+    /// une entrée de FAT est attendue, et non un cluster alloué
+    awaiting_fat: bool,
+}
+
+impl FileWrite {
+    /// écriture de `len` octets sur la chaîne `head`
+    pub(crate) fn new(head: u32, len: usize, cluster_size: u32) -> Self {
+        Self {
+            cluster_size: cluster_size as usize,
+            len,
+            offset: 0,
+            current: head,
+            awaiting_fat: false,
+        }
+    }
+    
+    /// étape suivante ; `answer` est l'entrée de FAT demandée par
+    /// `WriteStep::Fat` ou le cluster demandé par `WriteStep::Allocate`
+    pub(crate) fn step(&mut self, answer: Option<u32>) -> WriteStep {
+        match answer {
+            Some(next) if self.awaiting_fat && fat::is_eoc(next) => {
+                self.awaiting_fat = false;
+                return WriteStep::Allocate(self.current);
+            }
+            Some(next) => {
+                self.awaiting_fat = false;
+                self.current = next;
+            }
+            None if self.offset > 0 && self.offset < self.len => {
+                self.awaiting_fat = true;
+                return WriteStep::Fat(self.current);
+            }
+            None => {}
+        }
+        
+        if self.offset >= self.len {
+            return WriteStep::Done;
+        }
+        let size = self.cluster_size.min(self.len - self.offset);
+        let range = self.offset..self.offset + size;
+        self.offset = range.end;
+        
+        if size == self.cluster_size {
+            WriteStep::Cluster { cluster: self.current, range }
+        } else {
+            WriteStep::Head { cluster: self.current, range }
+        }
+    }
+}
+
+/// recherche de `length` clusters libres consécutifs, dans l'ordre
+/// croissant
+pub(crate) struct FreeRunSearch {
+    next: u32,
+    last: u32,
+    length: u32,
+    run_start: u32,
+    run_length: u32,
+}
+
+impl FreeRunSearch {
+    /// recherche entre `first` et `last` inclus
+    pub(crate) fn new(first: u32, last: u32, length: u32) -> Self {
+        Self {
+            next: first.max(2),
+            last,
+            length,
+            run_start: 0,
+            run_length: 0,
+        }
+    }
+    
+    /// prochain cluster à examiner, `None` si la recherche a échoué
+    pub(crate) fn next_cluster(&self) -> Option<u32> {
+        (self.next <= self.last).then_some(self.next)
+    }
+    
+    /// reçoit l'état du cluster examiné ; retourne le premier cluster de
+    /// la suite quand elle est complète
+    pub(crate) fn feed(&mut self, free: bool) -> Option<u32> {
+        let cluster = self.next;
+        self.next += 1;
+        
+        if !free {
+            self.run_length = 0;
+            return None;
+        }
+        if self.run_length == 0 {
+            self.run_start = cluster;
+        }
+        self.run_length += 1;
+        (self.run_length == self.length).then_some(self.run_start)
+    }
+}
+
+/// vérifie qu'une suite proposée pour l'allocation est dans la zone de
+/// données et retourne son dernier cluster
+pub(crate) fn check_run<E>(first: u32, length: u32, max_cluster: u32) -> Result<u32, Fat32Error<E>> {
+    if length == 0 {
+        return Err(Fat32Error::InvalidCluster { cluster: 0 });
+    }
+    let last = first.saturating_add(length - 1);
+    if first < 2 {
+        return Err(Fat32Error::InvalidCluster { cluster: first });
+    }
+    if last > max_cluster {
+        return Err(Fat32Error::InvalidCluster { cluster: last });
+    }
+    Ok(last)
+}
+
+/// entrées de FAT qui chaînent la suite `first..=last` et la rattachent
+/// à `previous`
+/// 
+/// la suite est chaînée avant d'être rattachée : une coupure entre les
+/// deux ne laisse qu'une chaîne perdue.
+pub(crate) fn run_links(first: u32, last: u32, previous: Option<u32>) -> impl Iterator<Item = (u32, u32)> {
+    (first..=last)
+        .map(move |cluster| (cluster, if cluster == last { fat::FAT_EOC } else { cluster + 1 }))
+        .chain(previous.map(|previous| (previous, first)))
+}
@@ -0,0 +1,138 @@
+//! lecture d'une image tronquée
+//! 
+//! une image copiée partiellement (ou produite par un outil qui n'écrit
+//! que le début du volume) est plus courte que ce qu'annonce son boot
+//! sector. montée en mode tolérant, elle reste lisible jusqu'à sa fin :
+//! les clusters situés au-delà sont considérés comme absents, et les
+//! fichiers ou répertoires qui en utilisent sont signalés comme coupés.
+
+use crate::operations::directory::{EntryLocation, Visit};
+use crate::operations::parser::Fat32Parser;
+use crate::structures::dir_entry::DirEntry;
+use crate::traits::block_device::BlockDevice;
+use crate::utils::error::Fat32Error;
+use crate::utils::fat;
+
+/// fichier ou répertoire coupé par la fin du dispositif
+#[derive(Debug, Clone, Copy)]
+pub struct TruncatedEntry<'a> {
+    /// entrée du fichier ou du répertoire
+    pub entry: &'a DirEntry,
+    /// position de l'entrée
+    pub entry_location: EntryLocation,
+    /// profondeur de l'entrée, 0 pour la racine
+    pub depth: usize,
+    /// octets lisibles au début du fichier ou du répertoire
+    pub readable: u32,
+}
+
+impl<D: BlockDevice> Fat32Parser<'_, D> {
+    /// retourne `true` si le volume dépasse la fin du dispositif
+    pub fn is_truncated(&self) -> bool {
+        self.device_sectors
+            .is_some_and(|sectors| sectors < self.geometry.total_sectors as u64)
+    }
+    
+    /// retourne le plus grand cluster entièrement présent sur le dispositif
+    /// 
+    /// égal à `max_cluster` si le volume n'est pas tronqué.
+    pub fn readable_max_cluster(&self) -> u32 {
+        match self.device_sectors {
+            Some(sectors) => self.geometry.last_cluster_before(sectors),
+            None => self.max_cluster(),
+        }
+    }
+    
+    /// vérifie si un cluster de données est entièrement présent sur le
+    /// dispositif
+    pub fn is_cluster_readable(&self, cluster: u32) -> bool {
+        (2..=self.readable_max_cluster()).contains(&cluster)
+    }
+    
+    /// retourne le nombre d'octets lisibles au début d'un fichier
+    /// 
+    /// vaut `file_size` sauf si l'un des clusters du fichier est au-delà
+    /// de la fin du dispositif : la lecture s'arrête alors au cluster
+    /// précédent.
+    pub fn readable_length(&self, entry: &DirEntry) -> Result<u32, Fat32Error<D::Error>> {
+        let cluster_size = self.boot_sector.cluster_size();
+        let size = entry.file_size;
+        if size == 0 || entry.first_cluster() < 2 {
+            return Ok(size);
+        }
+        
+        Ok(match self.first_missing_cluster(entry.first_cluster(), size.div_ceil(cluster_size))? {
+            Some(position) => position * cluster_size,
+            None => size,
+        })
+    }
+    
+    /// parcourt les fichiers et répertoires coupés par la fin du dispositif
+    /// 
+    /// un répertoire coupé n'est pas parcouru (voir `walk_tree`) : ses
+    /// fichiers ne sont pas signalés individuellement.
+    pub fn for_each_truncated<F>(&self, mut f: F) -> Result<(), Fat32Error<D::Error>>
+    where
+        F: FnMut(&TruncatedEntry) -> Result<Visit, Fat32Error<D::Error>>,
+    {
+        if !self.is_truncated() {
+            return Ok(());
+        }
+        let cluster_size = self.boot_sector.cluster_size();
+        
+        self.walk_tree(&mut |entry, location, depth| {
+            let head = entry.first_cluster();
+            if head < 2 {
+                return Ok(Visit::Continue);
+            }
+            
+            let readable = if entry.is_directory() {
+                self.first_missing_cluster(head, u32::MAX)?
+                    .map(|position| position.saturating_mul(cluster_size))
+            } else {
+                let readable = self.readable_length(entry)?;
+                (readable < entry.file_size).then_some(readable)
+            };
+            
+            match readable {
+                Some(readable) => f(&TruncatedEntry {
+                    entry,
+                    entry_location: location,
+                    depth,
+                    readable,
+                }),
+                None => Ok(Visit::Continue),
+            }
+        })?;
+        
+        Ok(())
+    }
+    
+    /// position dans la chaîne du premier cluster absent du dispositif,
+    /// parmi les `wanted` premiers
+    /// 
+    /// retourne `None` si ces clusters sont tous présents ou si la chaîne
+    /// se termine avant ; une chaîne incohérente est laissée au parcours
+    /// qui la lira.
+    pub(crate) fn first_missing_cluster(&self, head: u32, wanted: u32) -> Result<Option<u32>, Fat32Error<D::Error>> {
+        if !self.is_truncated() {
+            return Ok(None);
+        }
+        let max_cluster = self.max_cluster();
+        let mut current = head;
+        let mut position = 0;
+        
+        while position < wanted && !fat::is_eoc(current) {
+            if current < 2 || current > max_cluster || position > max_cluster {
+                return Ok(None);
+            }
+            if !self.is_cluster_readable(current) {
+                return Ok(Some(position));
+            }
+            position += 1;
+            current = self.read_fat_entry(current)?;
+        }
+        
+        Ok(None)
+    }
+}
@@ -0,0 +1,296 @@
+//! récupération de fichiers supprimés
+//! 
+//! une entrée supprimée garde son premier cluster et sa taille ; seul le
+//! premier octet du nom est remplacé par `ENTRY_DELETED` et la chaîne FAT
+//! est libérée. tant que les clusters n'ont pas été réutilisés, le
+//! fichier peut être restauré en supposant une allocation contiguë.
+
+use crate::operations::directory::{EntryLocation, Visit};
+use crate::operations::parser::Fat32Parser;
+use crate::structures::dir_entry::DirEntry;
+use crate::structures::lfn_entry::{LfnEntry, LongName, LFN_LAST_ENTRY, LFN_MAX_ENTRIES};
+use crate::traits::block_device::{BlockDevice, BlockDeviceMut};
+use crate::utils::constants::ENTRY_DELETED;
+use crate::utils::error::Fat32Error;
+use crate::utils::fat;
+use crate::utils::helpers::lfn_checksum;
+
+/// chances de récupération d'un fichier supprimé
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Recoverability {
+    /// fichier vide, seule l'entrée est à restaurer
+    NoData,
+    /// tous les clusters supposés sont encore libres
+    Recoverable,
+    /// le premier cluster est libre mais certains suivants sont réutilisés
+    PartiallyOverwritten {
+        free_clusters: u32,
+        total_clusters: u32,
+    },
+    /// le premier cluster a été réutilisé
+    Overwritten,
+}
+
+/// fichier supprimé trouvé dans un répertoire
+#[derive(Debug, Clone, Copy)]
+pub struct DeletedEntry {
+    /// entrée 8.3 telle que sur le disque
+    pub entry: DirEntry,
+    /// position de l'entrée 8.3
+    pub location: EntryLocation,
+    /// nom long, si les entrées LFN sont intactes
+    pub long_name: Option<LongName>,
+    /// premier octet du nom retrouvé grâce au checksum LFN
+    pub original_first_char: Option<u8>,
+    /// chances de récupération
+    pub recoverability: Recoverability,
+    lfn_locations: [EntryLocation; LFN_MAX_ENTRIES],
+    lfn_count: usize,
+    lfn_checksum: u8,
+}
+
+impl DeletedEntry {
+    /// premier octet à utiliser pour la restauration
+    /// 
+    /// retourne l'octet d'origine s'il est connu, sinon `_`.
+    pub fn suggested_first_char(&self) -> u8 {
+        self.original_first_char.unwrap_or(b'_')
+    }
+}
+
+/// entrées LFN supprimées en attente de leur entrée 8.3
+struct PendingLfn {
+    entries: [LfnEntry; LFN_MAX_ENTRIES],
+    locations: [EntryLocation; LFN_MAX_ENTRIES],
+    count: usize,
+    /// plus de `LFN_MAX_ENTRIES` entrées à la suite : aucun nom valide
+    overflow: bool,
+}
+
+impl PendingLfn {
+    fn new() -> Self {
+        Self {
+            entries: [LfnEntry::new(0, false, 0, &[]); LFN_MAX_ENTRIES],
+            locations: [EntryLocation { cluster: 0, sector: 0, index: 0 }; LFN_MAX_ENTRIES],
+            count: 0,
+            overflow: false,
+        }
+    }
+    
+    fn clear(&mut self) {
+        self.count = 0;
+        self.overflow = false;
+    }
+    
+    fn push(&mut self, entry: LfnEntry, location: EntryLocation) {
+        if self.count == LFN_MAX_ENTRIES {
+            self.count = 0;
+            self.overflow = true;
+        }
+        if self.overflow {
+            return;
+        }
+        self.entries[self.count] = entry;
+        self.locations[self.count] = location;
+        self.count += 1;
+    }
+    
+    /// reconstitue le nom long et le premier octet du nom court
+    /// 
+    /// les entrées sont dans l'ordre du disque : la dernière lue est la
+    /// première partie du nom. le checksum ne dépend bijectivement que du
+    /// premier octet une fois les 10 autres fixés, ce qui permet de le
+    /// retrouver.
+    fn resolve(&self, short_name: &[u8; 11]) -> Option<(LongName, u8)> {
+        if self.count == 0 || !self.is_sequence() {
+            return None;
+        }
+        
+        let checksum = self.entries[0].checksum;
+        if self.entries[..self.count].iter().any(|e| e.checksum != checksum) {
+            return None;
+        }
+        
+        let mut candidate = *short_name;
+        let first_char = (0x20..=0xFFu8).find(|&c| {
+            candidate[0] = c;
+            c != ENTRY_DELETED && lfn_checksum(&candidate) == checksum
+        })?;
+        
+        let mut name = LongName::new();
+        for sequence in 1..=self.count {
+            name.set_part(sequence, &self.entries[self.count - sequence].chars());
+        }
+        
+        Some((name, first_char))
+    }
+    
+    /// vérifie que les entrées forment les parties d'un même nom
+    /// 
+    /// la première entrée lue doit être la dernière partie, puis les
+    /// numéros décroissent jusqu'à 1. l'octet d'ordre est en général
+    /// remplacé par `ENTRY_DELETED` : il n'est alors plus vérifiable, et
+    /// seule la forme des parties compte. la fin du nom (0x0000 puis
+    /// bourrage 0xFFFF) ne peut figurer que dans la dernière partie, les
+    /// autres sont pleines.
+    fn is_sequence(&self) -> bool {
+        !self.overflow && self.entries[..self.count].iter().enumerate().all(|(i, entry)| {
+            let sequence = self.count - i;
+            let order = entry.order == ENTRY_DELETED
+                || (entry.sequence_number() as usize == sequence && entry.is_last() == (i == 0));
+            let full = entry.chars().iter().all(|&c| c != 0x0000 && c != 0xFFFF);
+            order && (i == 0 || full)
+        })
+    }
+}
+
+impl<D: BlockDevice> Fat32Parser<'_, D> {
+    /// liste les fichiers supprimés d'un répertoire
+    pub fn list_deleted<F>(&self, dir_cluster: u32, mut f: F) -> Result<(), Fat32Error<D::Error>>
+    where
+        F: FnMut(&DeletedEntry) -> Result<Visit, Fat32Error<D::Error>>,
+    {
+        let mut pending = PendingLfn::new();
+        
+        self.walk_dir(dir_cluster, &mut |entry, location| {
+            if entry.name[0] != ENTRY_DELETED {
+                pending.clear();
+                return Ok(Visit::Continue);
+            }
+            
+            if entry.is_long_name() {
+                pending.push(LfnEntry::from_bytes(&entry.to_bytes()), location);
+                return Ok(Visit::Continue);
+            }
+            
+            let mut deleted = DeletedEntry {
+                entry: *entry,
+                location,
+                long_name: None,
+                original_first_char: None,
+                recoverability: self.recoverability(entry)?,
+                lfn_locations: pending.locations,
+                lfn_count: 0,
+                lfn_checksum: 0,
+            };
+            
+            if let Some((name, first_char)) = pending.resolve(&entry.name) {
+                deleted.long_name = Some(name);
+                deleted.original_first_char = Some(first_char);
+                deleted.lfn_count = pending.count;
+                deleted.lfn_checksum = pending.entries[0].checksum;
+            }
+            pending.clear();
+            
+            f(&deleted)
+        })?;
+        
+        Ok(())
+    }
+    
+    /// nombre de clusters occupés par une entrée
+    fn entry_cluster_count(&self, entry: &DirEntry) -> u32 {
+        if entry.is_directory() {
+            1
+        } else {
+            self.file_size_in_clusters(entry.file_size)
+        }
+    }
+    
+    /// estime les chances de récupération d'une entrée supprimée
+    /// 
+    /// les clusters sont supposés contigus à partir du premier cluster.
+    pub fn recoverability(&self, entry: &DirEntry) -> Result<Recoverability, Fat32Error<D::Error>> {
+        let first = entry.first_cluster();
+        let total = self.entry_cluster_count(entry);
+        if first < 2 || total == 0 {
+            return Ok(Recoverability::NoData);
+        }
+        
+        let max_cluster = self.max_cluster();
+        let mut free = 0;
+        for cluster in first..first.saturating_add(total) {
+            if cluster <= max_cluster && fat::is_free(self.read_fat_entry(cluster)?) {
+                free += 1;
+            } else if cluster == first {
+                return Ok(Recoverability::Overwritten);
+            }
+        }
+        
+        if free == total {
+            Ok(Recoverability::Recoverable)
+        } else {
+            Ok(Recoverability::PartiallyOverwritten {
+                free_clusters: free,
+                total_clusters: total,
+            })
+        }
+    }
+}
+
+impl<D: BlockDeviceMut> Fat32Parser<'_, D> {
+    /// restaure un fichier supprimé
+    /// 
+    /// réécrit le premier octet du nom, reconstruit une chaîne contiguë et
+    /// restaure les entrées LFN retrouvées. retourne `AlreadyExists` si
+    /// des clusters du fichier ont été réutilisés, `NotFound` si l'entrée
+    /// ou l'une de ses entrées LFN a changé depuis la liste et
+    /// `InvalidName` si `first_char` ne peut pas commencer un nom.
+    pub fn undelete(&mut self, deleted: &DeletedEntry, first_char: u8) -> Result<(), Fat32Error<D::Error>> {
+        let mut entry = self.read_dir_entry(deleted.location)?;
+        if entry.name[0] != ENTRY_DELETED
+            || entry.name[1..] != deleted.entry.name[1..]
+            || entry.first_cluster() != deleted.entry.first_cluster()
+        {
+            return Err(Fat32Error::NotFound);
+        }
+        if first_char == ENTRY_DELETED || first_char < 0x20 {
+            return Err(Fat32Error::InvalidName);
+        }
+        
+        match self.recoverability(&entry)? {
+            Recoverability::NoData | Recoverability::Recoverable => {}
+            _ => return Err(Fat32Error::AlreadyExists),
+        }
+        
+        // les emplacements LFN ont pu être réutilisés depuis la liste
+        for &location in &deleted.lfn_locations[..deleted.lfn_count] {
+            let slot = self.read_dir_entry(location)?;
+            let lfn = LfnEntry::from_bytes(&slot.to_bytes());
+            if slot.name[0] != ENTRY_DELETED || !slot.is_long_name() || lfn.checksum != deleted.lfn_checksum {
+                return Err(Fat32Error::NotFound);
+            }
+        }
+        
+        let first = entry.first_cluster();
+        let count = self.entry_cluster_count(&entry);
+        if first >= 2 {
+            for cluster in first..first + count {
+                let next = if cluster + 1 == first + count { fat::FAT_EOC } else { cluster + 1 };
+                self.write_fat_entry(cluster, next)?;
+            }
+        }
+        
+        entry.name[0] = first_char;
+        let checksum = lfn_checksum(&entry.name);
+        
+        // entrées LFN : la plus proche de l'entrée 8.3 est la partie 1
+        for sequence in 1..=deleted.lfn_count {
+            let location = deleted.lfn_locations[deleted.lfn_count - sequence];
+            let raw = self.read_dir_entry(location)?.to_bytes();
+            let mut lfn = LfnEntry::from_bytes(&raw);
+            
+            lfn.order = sequence as u8;
+            if sequence == deleted.lfn_count {
+                lfn.order |= LFN_LAST_ENTRY;
+            }
+            lfn.checksum = checksum;
+            
+            let restored = DirEntry::from_bytes(&lfn.to_bytes());
+            self.write_dir_entry(location, &restored)?;
+        }
+        
+        self.write_dir_entry(deleted.location, &entry)?;
+        self.invalidate_free_count()
+    }
+}
@@ -0,0 +1,125 @@
+//! état du volume (bits de l'entrée FAT 1)
+//! 
+//! FAT32 réserve deux bits hauts de l'entrée 1 de la FAT : le bit 27 vaut
+//! 1 quand le volume a été démonté proprement, le bit 26 vaut 1 tant
+//! qu'aucune erreur matérielle n'a été rencontrée. le parser met le
+//! volume « sale » à la première écriture et le remet propre à `flush`,
+//! sauf s'il était déjà « sale » au montage.
+
+use crate::operations::parser::Fat32Parser;
+use crate::operations::steps;
+use crate::traits::block_device::{BlockDevice, BlockDeviceMut, SectorKind};
+use crate::utils::error::Fat32Error;
+
+/// bit de l'entrée FAT 1 : démontage propre
+pub const CLEAN_SHUTDOWN_BIT: u32 = 0x0800_0000;
+
+/// bit de l'entrée FAT 1 : pas d'erreur matérielle
+pub const NO_HARD_ERROR_BIT: u32 = 0x0400_0000;
+
+/// état du volume lu au montage
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct VolumeFlags {
+    /// le volume a été démonté proprement
+    pub clean_shutdown: bool,
+    /// une erreur d'entrée/sortie a été signalée
+    pub hard_error: bool,
+}
+
+impl VolumeFlags {
+    /// décode l'entrée FAT 1
+    pub fn from_fat_entry(entry: u32) -> Self {
+        Self {
+            clean_shutdown: entry & CLEAN_SHUTDOWN_BIT != 0,
+            hard_error: entry & NO_HARD_ERROR_BIT == 0,
+        }
+    }
+}
+
+impl<D: BlockDevice> Fat32Parser<'_, D> {
+    /// retourne l'état du volume lu au montage
+    pub fn volume_flags(&self) -> VolumeFlags {
+        self.volume_flags
+    }
+    
+    /// vérifie si le volume doit être contrôlé avant usage
+    /// 
+    /// vrai si le volume n'a pas été démonté proprement ou si une erreur
+    /// matérielle a été signalée.
+    pub fn needs_check(&self) -> bool {
+        !self.volume_flags.clean_shutdown || self.volume_flags.hard_error
+    }
+    
+    /// lit l'entrée FAT 1 sans masque
+    pub(crate) fn read_fat1(&self) -> Result<u32, Fat32Error<D::Error>> {
+        let mut buffer = [0u8; 512];
+        self.read_sector(self.geometry.fat_start, &mut buffer)?;
+        Ok(steps::fat1_entry(&buffer))
+    }
+}
+
+impl<D: BlockDeviceMut> Fat32Parser<'_, D> {
+    /// écrit sur le support les données en attente puis rend le dispositif
+    /// 
+    /// le volume est marqué propre.
+    pub fn unmount(mut self) -> Result<D, Fat32Error<D::Error>> {
+        self.flush()?;
+        Ok(self.device)
+    }
+    
+    /// marque le volume « sale » avant sa première modification
+    /// 
+    /// le bit est écrit et transmis au support tout de suite, même à
+    /// travers un cache différé : aucune modification ne peut l'y précéder.
+    pub(crate) fn mark_dirty(&mut self) -> Result<(), Fat32Error<D::Error>> {
+        let Some(patch) = steps::dirty_patch(self.dirty) else {
+            return Ok(());
+        };
+        self.update_fat1(patch)?;
+        self.device.flush()?;
+        self.dirty = true;
+        Ok(())
+    }
+    
+    /// déclare le volume sain après un contrôle
+    /// 
+    /// remet les bits de démontage propre et d'absence d'erreur, qui ne
+    /// sont sinon jamais rétablis sur un volume monté « sale ».
+    pub fn mark_volume_checked(&mut self) -> Result<(), Fat32Error<D::Error>> {
+        self.update_fat1(|entry| entry | CLEAN_SHUTDOWN_BIT | NO_HARD_ERROR_BIT)?;
+        self.volume_flags = VolumeFlags {
+            clean_shutdown: true,
+            hard_error: false,
+        };
+        self.dirty = false;
+        Ok(())
+    }
+    
+    /// remet le bit de démontage propre si le volume a été modifié
+    /// 
+    /// un volume déjà « sale » au montage le reste jusqu'à son contrôle.
+    pub(crate) fn mark_clean(&mut self) -> Result<(), Fat32Error<D::Error>> {
+        let Some(patch) = steps::clean_patch(self.dirty, self.volume_flags) else {
+            return Ok(());
+        };
+        self.update_fat1(patch)?;
+        self.dirty = false;
+        Ok(())
+    }
+    
+    /// modifie l'entrée FAT 1 dans chaque copie de la FAT
+    fn update_fat1<F>(&mut self, f: F) -> Result<(), Fat32Error<D::Error>>
+    where
+        F: Fn(u32) -> u32,
+    {
+        let mut buffer = [0u8; 512];
+        
+        for sector in steps::fat1_sectors(self.geometry) {
+            self.read_sector(sector, &mut buffer)?;
+            steps::patch_fat1(&mut buffer, &f);
+            self.write_sector_kind(sector, &buffer, SectorKind::Fat)?;
+        }
+        
+        Ok(())
+    }
+}
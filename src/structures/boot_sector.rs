@@ -4,8 +4,17 @@
 //! d'un volume FAT32. ce secteur contient toutes les informations
 //! nécessaires pour accéder au système de fichiers.
 
+use crate::utils::bytes::{array_at, head, head_mut, put_bytes, put_u16, put_u32, u16_at, u32_at};
+use crate::structures::geometry::Geometry;
+use crate::utils::error::Fat32Error;
+
+/// taille du boot sector sur le disque
+pub const BOOT_SECTOR_SIZE: usize = 512;
+
 /// boot sector (512 octets)
-#[repr(C, packed)]
+/// 
+/// les champs sont décodés un par un depuis leur position sur le disque ;
+/// la disposition en mémoire n'a pas à correspondre à celle du disque.
 #[derive(Debug, Clone, Copy)]
 pub struct BootSector {
     pub jmp_boot: [u8; 3],
@@ -20,6 +29,13 @@ pub struct BootSector {
     pub fat_size_16: u16,
     pub sectors_per_track: u16,
     pub num_heads: u16,
+    /// secteurs qui précédaient le volume sur le disque où il a été formaté
+    /// 
+    /// ignoré par le parser : le début du volume est toujours le
+    /// `partition_start` donné au montage. la valeur est souvent fausse
+    /// (partition extraite dans une image, disque recopié, volume formaté
+    /// sans table de partitions) et ne sert qu'à l'affichage ou à une
+    /// comparaison avec la table de partitions.
     pub hidden_sectors: u32,
     pub total_sectors_32: u32,
     
@@ -41,11 +57,88 @@ pub struct BootSector {
 }
 
 impl BootSector {
-    /// # Safety
-    /// - `data` doit contenir 512 octets valides
-    /// - utilise `read_unaligned` car la structure est `packed`
-    pub unsafe fn from_bytes(data: &[u8; 512]) -> Self {
-        core::ptr::read_unaligned(data.as_ptr() as *const BootSector)
+    /// décode un boot sector
+    /// 
+    /// retourne `BufferTooSmall` si `data` fait moins de 512 octets. la
+    /// signature n'est pas vérifiée, voir `is_valid`.
+    pub fn parse(data: &[u8]) -> Result<Self, Fat32Error> {
+        Ok(Self::from_bytes(head(data)?))
+    }
+    
+    /// encode le boot sector dans les 512 premiers octets de `out`
+    pub fn write_to(&self, out: &mut [u8]) -> Result<(), Fat32Error> {
+        *head_mut(out)? = self.to_bytes();
+        Ok(())
+    }
+    
+    /// décode un secteur complet
+    pub fn from_bytes(data: &[u8; BOOT_SECTOR_SIZE]) -> Self {
+        Self {
+            jmp_boot: array_at(data, 0),
+            oem_name: array_at(data, 3),
+            bytes_per_sector: u16_at(data, 11),
+            sectors_per_cluster: data[13],
+            reserved_sector_count: u16_at(data, 14),
+            num_fats: data[16],
+            root_entry_count: u16_at(data, 17),
+            total_sectors_16: u16_at(data, 19),
+            media_type: data[21],
+            fat_size_16: u16_at(data, 22),
+            sectors_per_track: u16_at(data, 24),
+            num_heads: u16_at(data, 26),
+            hidden_sectors: u32_at(data, 28),
+            total_sectors_32: u32_at(data, 32),
+            fat_size_32: u32_at(data, 36),
+            ext_flags: u16_at(data, 40),
+            fs_version: u16_at(data, 42),
+            root_cluster: u32_at(data, 44),
+            fs_info_sector: u16_at(data, 48),
+            backup_boot_sector: u16_at(data, 50),
+            reserved: array_at(data, 52),
+            drive_number: data[64],
+            reserved1: data[65],
+            boot_signature: data[66],
+            volume_id: u32_at(data, 67),
+            volume_label: array_at(data, 71),
+            fs_type: array_at(data, 82),
+            boot_code: array_at(data, 90),
+            signature: u16_at(data, 510),
+        }
+    }
+    
+    /// retourne les 512 octets du boot sector
+    pub fn to_bytes(&self) -> [u8; BOOT_SECTOR_SIZE] {
+        let mut out = [0u8; BOOT_SECTOR_SIZE];
+        put_bytes(&mut out, 0, &self.jmp_boot);
+        put_bytes(&mut out, 3, &self.oem_name);
+        put_u16(&mut out, 11, self.bytes_per_sector);
+        out[13] = self.sectors_per_cluster;
+        put_u16(&mut out, 14, self.reserved_sector_count);
+        out[16] = self.num_fats;
+        put_u16(&mut out, 17, self.root_entry_count);
+        put_u16(&mut out, 19, self.total_sectors_16);
+        out[21] = self.media_type;
+        put_u16(&mut out, 22, self.fat_size_16);
+        put_u16(&mut out, 24, self.sectors_per_track);
+        put_u16(&mut out, 26, self.num_heads);
+        put_u32(&mut out, 28, self.hidden_sectors);
+        put_u32(&mut out, 32, self.total_sectors_32);
+        put_u32(&mut out, 36, self.fat_size_32);
+        put_u16(&mut out, 40, self.ext_flags);
+        put_u16(&mut out, 42, self.fs_version);
+        put_u32(&mut out, 44, self.root_cluster);
+        put_u16(&mut out, 48, self.fs_info_sector);
+        put_u16(&mut out, 50, self.backup_boot_sector);
+        put_bytes(&mut out, 52, &self.reserved);
+        out[64] = self.drive_number;
+        out[65] = self.reserved1;
+        out[66] = self.boot_signature;
+        put_u32(&mut out, 67, self.volume_id);
+        put_bytes(&mut out, 71, &self.volume_label);
+        put_bytes(&mut out, 82, &self.fs_type);
+        put_bytes(&mut out, 90, &self.boot_code);
+        put_u16(&mut out, 510, self.signature);
+        out
     }
     
     pub fn is_valid(&self) -> bool {
@@ -62,14 +155,40 @@ impl BootSector {
         self.reserved_sector_count as u32
     }
     
+    /// retourne la géométrie du volume (régions, nombre de clusters)
+    pub fn geometry(&self) -> Geometry {
+        Geometry::new(self)
+    }
+    
     /// retourne le secteur de début de la zone de données
+    /// 
+    /// sature à `u32::MAX` pour un boot sector incohérent.
     pub fn data_start_sector(&self) -> u32 {
-        self.reserved_sector_count as u32 + (self.num_fats as u32 * self.fat_size_32)
+        self.geometry().data_start
     }
     
     /// convertit un numéro de cluster en secteur
+    /// 
+    /// `cluster` doit être un cluster de données ; le calcul sature sinon.
+    /// voir `checked_cluster_to_sector` pour une valeur venant du disque.
     pub fn cluster_to_sector(&self, cluster: u32) -> u32 {
-        self.data_start_sector() + ((cluster - 2) * self.sectors_per_cluster as u32)
+        cluster
+            .saturating_sub(2)
+            .saturating_mul(self.sectors_per_cluster as u32)
+            .saturating_add(self.data_start_sector())
+    }
+    
+    /// convertit un numéro de cluster en secteur, ou `None` si le cluster
+    /// est hors de la zone de données
+    pub fn checked_cluster_to_sector(&self, cluster: u32) -> Option<u32> {
+        self.geometry().cluster_to_sector(cluster)
+    }
+    
+    /// retourne le plus grand numéro de cluster de données valide
+    /// 
+    /// vaut 1 (aucun cluster) si le boot sector n'a pas de zone de données.
+    pub fn max_cluster(&self) -> u32 {
+        self.geometry().max_cluster()
     }
     
     /// retourne le nombre total de secteurs
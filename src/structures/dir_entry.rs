@@ -3,8 +3,13 @@
 //! ce module contient la structure DirEntry qui représente une entrée
 //! dans un répertoire FAT32 (fichier ou sous-répertoire).
 
+use crate::utils::bytes::{array_at, head, head_mut, put_bytes, put_u16, put_u32, u16_at, u32_at};
+use crate::utils::error::Fat32Error;
+
+/// taille d'une entrée de répertoire
+pub const DIR_ENTRY_SIZE: usize = 32;
+
 /// entrée de répertoire (32 octets)
-#[repr(C, packed)]
 #[derive(Debug, Clone, Copy)]
 pub struct DirEntry {
     pub name: [u8; 11],
@@ -31,10 +36,63 @@ pub const ATTR_ARCHIVE: u8 = 0x20;
 pub const ATTR_LONG_NAME: u8 = 0x0F;
 
 impl DirEntry {
+    /// décode une entrée depuis les 32 premiers octets de `data`
+    pub fn parse(data: &[u8]) -> Result<Self, Fat32Error> {
+        Ok(Self::from_bytes(head(data)?))
+    }
+    
+    /// encode l'entrée dans les 32 premiers octets de `out`
+    pub fn write_to(&self, out: &mut [u8]) -> Result<(), Fat32Error> {
+        *head_mut(out)? = self.to_bytes();
+        Ok(())
+    }
+    
+    /// décode une entrée complète
+    pub fn from_bytes(data: &[u8; DIR_ENTRY_SIZE]) -> Self {
+        Self {
+            name: array_at(data, 0),
+            attributes: data[11],
+            nt_reserved: data[12],
+            creation_time_tenth: data[13],
+            creation_time: u16_at(data, 14),
+            creation_date: u16_at(data, 16),
+            last_access_date: u16_at(data, 18),
+            first_cluster_high: u16_at(data, 20),
+            last_write_time: u16_at(data, 22),
+            last_write_date: u16_at(data, 24),
+            first_cluster_low: u16_at(data, 26),
+            file_size: u32_at(data, 28),
+        }
+    }
+    
+    /// retourne les 32 octets de l'entrée
+    pub fn to_bytes(&self) -> [u8; DIR_ENTRY_SIZE] {
+        let mut out = [0u8; DIR_ENTRY_SIZE];
+        put_bytes(&mut out, 0, &self.name);
+        out[11] = self.attributes;
+        out[12] = self.nt_reserved;
+        out[13] = self.creation_time_tenth;
+        put_u16(&mut out, 14, self.creation_time);
+        put_u16(&mut out, 16, self.creation_date);
+        put_u16(&mut out, 18, self.last_access_date);
+        put_u16(&mut out, 20, self.first_cluster_high);
+        put_u16(&mut out, 22, self.last_write_time);
+        put_u16(&mut out, 24, self.last_write_date);
+        put_u16(&mut out, 26, self.first_cluster_low);
+        put_u32(&mut out, 28, self.file_size);
+        out
+    }
+    
     pub fn first_cluster(&self) -> u32 {
         ((self.first_cluster_high as u32) << 16) | (self.first_cluster_low as u32)
     }
     
+    /// modifie le premier cluster
+    pub fn set_first_cluster(&mut self, cluster: u32) {
+        self.first_cluster_high = (cluster >> 16) as u16;
+        self.first_cluster_low = (cluster & 0xFFFF) as u16;
+    }
+    
     pub fn is_directory(&self) -> bool {
         self.attributes & ATTR_DIRECTORY != 0
     }
@@ -44,7 +102,7 @@ impl DirEntry {
     }
     
     pub fn is_empty(&self) -> bool {
-        self.name[0] == crate::utils::constants::ENTRY_EMPTY 
+        self.name[0] == crate::utils::constants::ENTRY_EMPTY
             || self.name[0] == crate::utils::constants::ENTRY_DELETED
     }
     
@@ -3,8 +3,14 @@
 //! le secteur FSInfo contient des informations sur l'état du système
 //! de fichiers, notamment le nombre de clusters libres.
 
+use crate::utils::bytes::{array_at, head, head_mut, put_bytes, put_u32, u32_at};
+use crate::utils::constants::{FSINFO_LEAD_SIG, FSINFO_STRUCT_SIG, FSINFO_TRAIL_SIG};
+use crate::utils::error::Fat32Error;
+
+/// taille du secteur FSInfo sur le disque
+pub const FSINFO_SIZE: usize = 512;
+
 /// FSInfo (512 octets)
-#[repr(C, packed)]
 #[derive(Debug, Clone, Copy)]
 pub struct FSInfo {
     pub lead_signature: u32,        // 0x41615252
@@ -17,17 +23,50 @@ pub struct FSInfo {
 }
 
 impl FSInfo {
-    /// # Safety
-    /// - `data` doit contenir 512 octets valides
-    /// - utilise `read_unaligned` car la structure est `packed`
-    pub unsafe fn from_bytes(data: &[u8; 512]) -> Self {
-        core::ptr::read_unaligned(data.as_ptr() as *const FSInfo)
+    /// décode un secteur FSInfo
+    /// 
+    /// retourne `BufferTooSmall` si `data` fait moins de 512 octets. les
+    /// signatures ne sont pas vérifiées, voir `is_valid`.
+    pub fn parse(data: &[u8]) -> Result<Self, Fat32Error> {
+        Ok(Self::from_bytes(head(data)?))
+    }
+    
+    /// encode le secteur FSInfo dans les 512 premiers octets de `out`
+    pub fn write_to(&self, out: &mut [u8]) -> Result<(), Fat32Error> {
+        *head_mut(out)? = self.to_bytes();
+        Ok(())
+    }
+    
+    /// décode un secteur complet
+    pub fn from_bytes(data: &[u8; FSINFO_SIZE]) -> Self {
+        Self {
+            lead_signature: u32_at(data, 0),
+            reserved1: array_at(data, 4),
+            struct_signature: u32_at(data, 484),
+            free_count: u32_at(data, 488),
+            next_free: u32_at(data, 492),
+            reserved2: array_at(data, 496),
+            trail_signature: u32_at(data, 508),
+        }
+    }
+    
+    /// retourne les 512 octets du secteur FSInfo
+    pub fn to_bytes(&self) -> [u8; FSINFO_SIZE] {
+        let mut out = [0u8; FSINFO_SIZE];
+        put_u32(&mut out, 0, self.lead_signature);
+        put_bytes(&mut out, 4, &self.reserved1);
+        put_u32(&mut out, 484, self.struct_signature);
+        put_u32(&mut out, 488, self.free_count);
+        put_u32(&mut out, 492, self.next_free);
+        put_bytes(&mut out, 496, &self.reserved2);
+        put_u32(&mut out, 508, self.trail_signature);
+        out
     }
     
     pub fn is_valid(&self) -> bool {
-        self.lead_signature == 0x41615252 
-            && self.struct_signature == 0x61417772
-            && self.trail_signature == 0xAA550000
+        self.lead_signature == FSINFO_LEAD_SIG
+            && self.struct_signature == FSINFO_STRUCT_SIG
+            && self.trail_signature == FSINFO_TRAIL_SIG
     }
     
     /// retourne le nombre de clusters libres
@@ -0,0 +1,197 @@
+//! géométrie d'un volume
+//! 
+//! `Geometry` regroupe les valeurs dérivées du boot sector : limites des
+//! régions, nombre exact de clusters de données et entrées de FAT
+//! utilisées. les calculs saturent : un boot sector incohérent donne un
+//! volume sans cluster plutôt qu'un débordement.
+
+use crate::structures::boot_sector::BootSector;
+use crate::utils::constants::SECTOR_SIZE;
+
+/// nombre d'entrées FAT32 par secteur
+pub const FAT_ENTRIES_PER_SECTOR: u32 = (SECTOR_SIZE / 4) as u32;
+
+/// nombre maximal d'entrées d'une FAT32 : au-delà de 0x0FFFFFF6, les
+/// valeurs sont réservées (cluster défectueux, fin de chaîne)
+pub const MAX_FAT32_ENTRIES: u32 = 0x0FFF_FFF7;
+
+/// type de FAT, déterminé par le nombre de clusters de données
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FatType {
+    /// moins de 4085 clusters
+    Fat12,
+    /// moins de 65525 clusters
+    Fat16,
+    /// 65525 clusters ou plus
+    Fat32,
+}
+
+/// région du volume contenant un secteur
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Region {
+    /// secteurs réservés (boot sector, FSInfo, copie de secours)
+    Reserved,
+    /// copie `copy` de la FAT
+    Fat { copy: u32 },
+    /// cluster de données
+    Data { cluster: u32 },
+    /// fin de la zone de données, trop courte pour un cluster ou hors de
+    /// la FAT
+    Unused,
+    /// au-delà de la fin du volume
+    Beyond,
+}
+
+/// géométrie d'un volume FAT32
+/// 
+/// les numéros de secteur sont relatifs au début du volume.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Geometry {
+    /// secteurs par cluster
+    pub sectors_per_cluster: u32,
+    /// premier secteur de la première FAT
+    pub fat_start: u32,
+    /// taille d'une FAT en secteurs
+    pub fat_size: u32,
+    /// nombre de copies de la FAT
+    pub num_fats: u32,
+    /// premier secteur de la zone de données
+    pub data_start: u32,
+    /// nombre total de secteurs du volume
+    pub total_sectors: u32,
+    /// nombre de clusters entiers dans la zone de données
+    pub cluster_count: u32,
+    /// nombre d'entrées de FAT utilisées, les deux entrées réservées
+    /// comprises ; limité par la taille de la FAT
+    pub fat_entries: u32,
+}
+
+impl Geometry {
+    /// calcule la géométrie décrite par un boot sector
+    pub fn new(bs: &BootSector) -> Self {
+        let sectors_per_cluster = bs.sectors_per_cluster as u32;
+        let fat_start = bs.reserved_sector_count as u32;
+        let fat_size = bs.fat_size();
+        let num_fats = bs.num_fats as u32;
+        let data_start = num_fats.saturating_mul(fat_size).saturating_add(fat_start);
+        let total_sectors = bs.total_sectors();
+        
+        let cluster_count = total_sectors
+            .saturating_sub(data_start)
+            .checked_div(sectors_per_cluster)
+            .unwrap_or(0);
+        let fat_entries = cluster_count
+            .saturating_add(2)
+            .min(fat_size.saturating_mul(FAT_ENTRIES_PER_SECTOR))
+            .min(MAX_FAT32_ENTRIES);
+        
+        Self {
+            sectors_per_cluster,
+            fat_start,
+            fat_size,
+            num_fats,
+            data_start,
+            total_sectors,
+            cluster_count,
+            fat_entries,
+        }
+    }
+    
+    /// retourne le plus grand numéro de cluster de données valide
+    /// 
+    /// vaut 1 (aucun cluster) si le volume n'a pas de zone de données.
+    pub fn max_cluster(&self) -> u32 {
+        self.fat_entries.saturating_sub(1).max(1)
+    }
+    
+    /// vérifie si `cluster` est un cluster de données du volume
+    pub fn contains_cluster(&self, cluster: u32) -> bool {
+        (2..=self.max_cluster()).contains(&cluster)
+    }
+    
+    /// retourne le type de FAT correspondant au nombre de clusters
+    pub fn fat_type(&self) -> FatType {
+        match self.cluster_count {
+            0..4085 => FatType::Fat12,
+            4085..65525 => FatType::Fat16,
+            _ => FatType::Fat32,
+        }
+    }
+    
+    /// retourne le premier secteur de la copie `copy` de la FAT
+    pub fn fat_copy_start(&self, copy: u32) -> u32 {
+        copy.saturating_mul(self.fat_size).saturating_add(self.fat_start)
+    }
+    
+    /// retourne le nombre de secteurs de FAT nécessaires pour tous les
+    /// clusters de la zone de données
+    pub fn fat_sectors_needed(&self) -> u32 {
+        (self.cluster_count as u64 + 2).div_ceil(FAT_ENTRIES_PER_SECTOR as u64).min(u32::MAX as u64) as u32
+    }
+    
+    /// retourne le nombre de secteurs de FAT contenant des entrées utilisées
+    pub fn fat_sectors_used(&self) -> u32 {
+        self.fat_entries.div_ceil(FAT_ENTRIES_PER_SECTOR)
+    }
+    
+    /// retourne la fin de la zone de données utilisable (premier secteur
+    /// après le dernier cluster)
+    pub fn data_end(&self) -> u32 {
+        self.max_cluster()
+            .saturating_sub(1)
+            .saturating_mul(self.sectors_per_cluster)
+            .saturating_add(self.data_start)
+    }
+    
+    /// retourne le plus grand cluster dont tous les secteurs précèdent le
+    /// secteur `end`
+    /// 
+    /// sert à borner la lecture d'une image tronquée à `end` secteurs ;
+    /// vaut 1 si aucun cluster n'est entier avant `end`.
+    pub fn last_cluster_before(&self, end: u64) -> u32 {
+        let whole = end
+            .saturating_sub(self.data_start as u64)
+            .checked_div(self.sectors_per_cluster as u64)
+            .unwrap_or(0);
+        whole.saturating_add(1).min(self.max_cluster() as u64) as u32
+    }
+    
+    /// convertit un numéro de cluster en secteur, ou `None` si le cluster
+    /// est hors de la zone de données
+    pub fn cluster_to_sector(&self, cluster: u32) -> Option<u32> {
+        if !self.contains_cluster(cluster) {
+            return None;
+        }
+        (cluster - 2)
+            .checked_mul(self.sectors_per_cluster)?
+            .checked_add(self.data_start)
+    }
+    
+    /// position de l'entrée d'un cluster dans la première FAT
+    /// 
+    /// retourne le secteur relatif au volume et l'octet dans ce secteur, ou
+    /// `None` si le cluster est hors de la zone de données.
+    pub fn fat_entry_position(&self, cluster: u32) -> Option<(u32, usize)> {
+        if !self.contains_cluster(cluster) {
+            return None;
+        }
+        let sector = cluster / FAT_ENTRIES_PER_SECTOR;
+        let offset = (cluster % FAT_ENTRIES_PER_SECTOR) as usize * 4;
+        Some((self.fat_start + sector, offset))
+    }
+    
+    /// retourne la région contenant `sector`
+    pub fn region(&self, sector: u32) -> Region {
+        if sector >= self.total_sectors {
+            Region::Beyond
+        } else if sector < self.fat_start {
+            Region::Reserved
+        } else if sector < self.data_start {
+            Region::Fat { copy: (sector - self.fat_start) / self.fat_size }
+        } else if sector < self.data_end() {
+            Region::Data { cluster: (sector - self.data_start) / self.sectors_per_cluster + 2 }
+        } else {
+            Region::Unused
+        }
+    }
+}
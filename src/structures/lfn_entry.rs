@@ -0,0 +1,223 @@
+//! entrées de nom long (LFN)
+//! 
+//! un nom long est stocké en UCS-2 dans une suite d'entrées de 32 octets
+//! placées juste avant l'entrée 8.3 du fichier, de la dernière partie
+//! à la première.
+
+use core::fmt;
+use crate::structures::dir_entry::DIR_ENTRY_SIZE;
+use crate::utils::bytes::{array_at, head, head_mut, put_bytes, put_u16, u16_at};
+use crate::utils::error::Fat32Error;
+
+/// entrée de nom long (32 octets)
+#[derive(Debug, Clone, Copy)]
+pub struct LfnEntry {
+    pub order: u8,
+    pub name1: [u8; 10],
+    pub attributes: u8,
+    pub entry_type: u8,
+    pub checksum: u8,
+    pub name2: [u8; 12],
+    pub first_cluster_low: u16,
+    pub name3: [u8; 4],
+}
+
+/// bit marquant la dernière partie d'un nom long
+pub const LFN_LAST_ENTRY: u8 = 0x40;
+
+/// nombre de caractères UCS-2 par entrée LFN
+pub const LFN_CHARS_PER_ENTRY: usize = 13;
+
+/// nombre maximal d'entrées LFN pour un nom
+pub const LFN_MAX_ENTRIES: usize = 20;
+
+/// longueur maximale d'un nom long
+pub const LFN_MAX_LEN: usize = 255;
+
+impl LfnEntry {
+    /// décode une entrée depuis les 32 premiers octets de `data`
+    pub fn parse(data: &[u8]) -> Result<Self, Fat32Error> {
+        Ok(Self::from_bytes(head(data)?))
+    }
+    
+    /// encode l'entrée dans les 32 premiers octets de `out`
+    pub fn write_to(&self, out: &mut [u8]) -> Result<(), Fat32Error> {
+        *head_mut(out)? = self.to_bytes();
+        Ok(())
+    }
+    
+    /// décode une entrée complète
+    pub fn from_bytes(data: &[u8; DIR_ENTRY_SIZE]) -> Self {
+        Self {
+            order: data[0],
+            name1: array_at(data, 1),
+            attributes: data[11],
+            entry_type: data[12],
+            checksum: data[13],
+            name2: array_at(data, 14),
+            first_cluster_low: u16_at(data, 26),
+            name3: array_at(data, 28),
+        }
+    }
+    
+    /// retourne les 32 octets de l'entrée
+    pub fn to_bytes(&self) -> [u8; DIR_ENTRY_SIZE] {
+        let mut out = [0u8; DIR_ENTRY_SIZE];
+        out[0] = self.order;
+        put_bytes(&mut out, 1, &self.name1);
+        out[11] = self.attributes;
+        out[12] = self.entry_type;
+        out[13] = self.checksum;
+        put_bytes(&mut out, 14, &self.name2);
+        put_u16(&mut out, 26, self.first_cluster_low);
+        put_bytes(&mut out, 28, &self.name3);
+        out
+    }
+    
+    /// crée une partie de nom long
+    /// 
+    /// `chars` est complété par un 0x0000 puis des 0xFFFF s'il contient
+    /// moins de 13 caractères.
+    pub fn new(sequence: u8, is_last: bool, checksum: u8, chars: &[u16]) -> Self {
+        let mut units = [0xFFFFu16; LFN_CHARS_PER_ENTRY];
+        for (i, unit) in units.iter_mut().enumerate() {
+            match i.cmp(&chars.len()) {
+                core::cmp::Ordering::Less => *unit = chars[i],
+                core::cmp::Ordering::Equal => *unit = 0x0000,
+                core::cmp::Ordering::Greater => break,
+            }
+        }
+        
+        let mut bytes = [0u8; 26];
+        for (i, unit) in units.iter().enumerate() {
+            bytes[i * 2..i * 2 + 2].copy_from_slice(&unit.to_le_bytes());
+        }
+        
+        let mut name1 = [0u8; 10];
+        let mut name2 = [0u8; 12];
+        let mut name3 = [0u8; 4];
+        name1.copy_from_slice(&bytes[0..10]);
+        name2.copy_from_slice(&bytes[10..22]);
+        name3.copy_from_slice(&bytes[22..26]);
+        
+        Self {
+            order: if is_last { sequence | LFN_LAST_ENTRY } else { sequence },
+            name1,
+            attributes: crate::structures::dir_entry::ATTR_LONG_NAME,
+            entry_type: 0,
+            checksum,
+            name2,
+            first_cluster_low: 0,
+            name3,
+        }
+    }
+    
+    /// retourne le numéro de la partie (à partir de 1)
+    pub fn sequence_number(&self) -> u8 {
+        self.order & 0x1F
+    }
+    
+    /// vérifie si c'est la dernière partie du nom
+    pub fn is_last(&self) -> bool {
+        self.order & LFN_LAST_ENTRY != 0
+    }
+    
+    /// retourne les 13 caractères UCS-2 de l'entrée
+    pub fn chars(&self) -> [u16; LFN_CHARS_PER_ENTRY] {
+        let mut bytes = [0u8; 26];
+        bytes[0..10].copy_from_slice(&self.name1);
+        bytes[10..22].copy_from_slice(&self.name2);
+        bytes[22..26].copy_from_slice(&self.name3);
+        
+        let mut chars = [0u16; LFN_CHARS_PER_ENTRY];
+        for (i, c) in chars.iter_mut().enumerate() {
+            *c = u16::from_le_bytes([bytes[i * 2], bytes[i * 2 + 1]]);
+        }
+        chars
+    }
+}
+
+/// nom long reconstitué à partir des entrées LFN
+#[derive(Clone, Copy)]
+pub struct LongName {
+    chars: [u16; LFN_MAX_LEN],
+    len: usize,
+}
+
+impl LongName {
+    /// crée un nom vide
+    pub fn new() -> Self {
+        Self {
+            chars: [0; LFN_MAX_LEN],
+            len: 0,
+        }
+    }
+    
+    /// place les caractères d'une partie de nom (numérotée à partir de 1)
+    pub fn set_part(&mut self, sequence: usize, chars: &[u16; LFN_CHARS_PER_ENTRY]) {
+        if sequence == 0 {
+            return;
+        }
+        
+        let start = (sequence - 1) * LFN_CHARS_PER_ENTRY;
+        for (i, &c) in chars.iter().enumerate() {
+            let position = start + i;
+            if c == 0x0000 || c == 0xFFFF || position >= LFN_MAX_LEN {
+                break;
+            }
+            self.chars[position] = c;
+            self.len = core::cmp::max(self.len, position + 1);
+        }
+    }
+    
+    /// retourne les caractères UCS-2 du nom
+    pub fn as_ucs2(&self) -> &[u16] {
+        &self.chars[..self.len]
+    }
+    
+    /// vérifie si le nom ne contient aucun caractère
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+    
+    /// écrit le nom en UTF-8 dans `out` et retourne le nombre d'octets écrits
+    /// 
+    /// le nom est tronqué si `out` est trop petit.
+    pub fn to_utf8(&self, out: &mut [u8]) -> usize {
+        let mut written = 0;
+        
+        for c in char::decode_utf16(self.as_ucs2().iter().copied()) {
+            let c = c.unwrap_or(char::REPLACEMENT_CHARACTER);
+            if written + c.len_utf8() > out.len() {
+                break;
+            }
+            written += c.encode_utf8(&mut out[written..]).len();
+        }
+        
+        written
+    }
+    
+    /// retourne le nom en UTF-8, les caractères invalides étant remplacés
+    #[cfg(feature = "alloc")]
+    pub fn to_string_lossy(&self) -> alloc::string::String {
+        char::decode_utf16(self.as_ucs2().iter().copied())
+            .map(|c| c.unwrap_or(char::REPLACEMENT_CHARACTER))
+            .collect()
+    }
+}
+
+impl Default for LongName {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl fmt::Debug for LongName {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "\"")?;
+        for c in char::decode_utf16(self.as_ucs2().iter().copied()) {
+            write!(f, "{}", c.unwrap_or(char::REPLACEMENT_CHARACTER))?;
+        }
+        write!(f, "\"")
+    }
+}
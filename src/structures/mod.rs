@@ -1,6 +1,8 @@
 //! structures de données FAT32
 
 pub mod boot_sector;
+pub mod geometry;
 pub mod fsinfo;
 pub mod dir_entry;
+pub mod lfn_entry;
 
@@ -0,0 +1,146 @@
+//! trait pour les dispositifs de stockage asynchrones
+//! 
+//! pendant de `BlockDevice` pour les supports dont les transferts se
+//! terminent de façon asynchrone (contrôleur DMA, réseau, exécuteur
+//! embarqué). les méthodes retournent des futures, sans dépendre d'un
+//! exécuteur particulier.
+
+use core::future::Future;
+use crate::traits::block_device::{BlockDevice, BlockDeviceMut, SectorKind};
+use crate::utils::error::Fat32Error;
+
+/// trait pour un dispositif bloc asynchrone lisible
+/// 
+/// # Exemples
+/// 
+/// ```no_run
+/// use fat32_parser::error::Fat32Error;
+/// use fat32_parser::traits::async_block_device::{AsyncBlockDevice, AsyncBlockDeviceMut};
+/// 
+/// struct MonDevice;
+/// 
+/// impl AsyncBlockDevice for MonDevice {
+///     type Error = core::convert::Infallible;
+/// 
+///     async fn read_sector(&self, sector: u64, buffer: &mut [u8]) -> Result<(), Fat32Error> {
+///         // lancement du transfert puis attente de sa fin
+///         Ok(())
+///     }
+/// }
+/// 
+/// impl AsyncBlockDeviceMut for MonDevice {
+///     async fn write_sector(&mut self, sector: u64, buffer: &[u8]) -> Result<(), Fat32Error> {
+///         Ok(())
+///     }
+/// }
+/// ```
+pub trait AsyncBlockDevice {
+    /// erreur propre au dispositif
+    type Error: core::fmt::Debug;
+    
+    /// lit un secteur
+    fn read_sector(&self, sector: u64, buffer: &mut [u8]) -> impl Future<Output = Result<(), Fat32Error<Self::Error>>>;
+    
+    /// lit plusieurs secteurs consécutifs à partir de `start`
+    /// 
+    /// `buffer` doit faire un multiple de 512 octets. par défaut, lit les
+    /// secteurs un par un.
+    fn read_sectors(&self, start: u64, buffer: &mut [u8]) -> impl Future<Output = Result<(), Fat32Error<Self::Error>>> {
+        async move {
+            if !buffer.len().is_multiple_of(512) {
+                return Err(Fat32Error::BufferTooSmall);
+            }
+            for (i, chunk) in buffer.chunks_exact_mut(512).enumerate() {
+                self.read_sector(start + i as u64, chunk).await?;
+            }
+            Ok(())
+        }
+    }
+    
+    /// retourne le nombre de secteurs du dispositif, s'il est connu
+    fn sector_count(&self) -> Option<u64> {
+        None
+    }
+}
+
+/// trait pour un dispositif bloc asynchrone accessible en écriture
+pub trait AsyncBlockDeviceMut: AsyncBlockDevice {
+    /// écrit un secteur
+    fn write_sector(&mut self, sector: u64, buffer: &[u8]) -> impl Future<Output = Result<(), Fat32Error<Self::Error>>>;
+    
+    /// écrit plusieurs secteurs consécutifs à partir de `start`
+    /// 
+    /// `buffer` doit faire un multiple de 512 octets. par défaut, écrit les
+    /// secteurs un par un.
+    fn write_sectors(&mut self, start: u64, buffer: &[u8]) -> impl Future<Output = Result<(), Fat32Error<Self::Error>>> {
+        async move {
+            if !buffer.len().is_multiple_of(512) {
+                return Err(Fat32Error::BufferTooSmall);
+            }
+            for (i, chunk) in buffer.chunks_exact(512).enumerate() {
+                self.write_sector(start + i as u64, chunk).await?;
+            }
+            Ok(())
+        }
+    }
+    
+    /// écrit un secteur en précisant sa nature
+    fn write_sector_kind(
+        &mut self,
+        sector: u64,
+        buffer: &[u8],
+        _kind: SectorKind,
+    ) -> impl Future<Output = Result<(), Fat32Error<Self::Error>>> {
+        self.write_sector(sector, buffer)
+    }
+    
+    /// écrit sur le support les données encore en attente
+    fn flush(&mut self) -> impl Future<Output = Result<(), Fat32Error<Self::Error>>> {
+        core::future::ready(Ok(()))
+    }
+}
+
+/// adaptateur exposant un `BlockDevice` synchrone comme asynchrone
+/// 
+/// chaque opération se termine immédiatement ; utile pour réutiliser les
+/// dispositifs existants avec `AsyncFat32Parser`.
+pub struct Blocking<D: BlockDevice>(pub D);
+
+impl<D: BlockDevice> AsyncBlockDevice for Blocking<D> {
+    type Error = D::Error;
+    
+    fn read_sector(&self, sector: u64, buffer: &mut [u8]) -> impl Future<Output = Result<(), Fat32Error<Self::Error>>> {
+        core::future::ready(self.0.read_sector(sector, buffer))
+    }
+    
+    fn read_sectors(&self, start: u64, buffer: &mut [u8]) -> impl Future<Output = Result<(), Fat32Error<Self::Error>>> {
+        core::future::ready(self.0.read_sectors(start, buffer))
+    }
+    
+    fn sector_count(&self) -> Option<u64> {
+        self.0.sector_count()
+    }
+}
+
+impl<D: BlockDeviceMut> AsyncBlockDeviceMut for Blocking<D> {
+    fn write_sector(&mut self, sector: u64, buffer: &[u8]) -> impl Future<Output = Result<(), Fat32Error<Self::Error>>> {
+        core::future::ready(self.0.write_sector(sector, buffer))
+    }
+    
+    fn write_sectors(&mut self, start: u64, buffer: &[u8]) -> impl Future<Output = Result<(), Fat32Error<Self::Error>>> {
+        core::future::ready(self.0.write_sectors(start, buffer))
+    }
+    
+    fn write_sector_kind(
+        &mut self,
+        sector: u64,
+        buffer: &[u8],
+        kind: SectorKind,
+    ) -> impl Future<Output = Result<(), Fat32Error<Self::Error>>> {
+        core::future::ready(self.0.write_sector_kind(sector, buffer, kind))
+    }
+    
+    fn flush(&mut self) -> impl Future<Output = Result<(), Fat32Error<Self::Error>>> {
+        core::future::ready(self.0.flush())
+    }
+}
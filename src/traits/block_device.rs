@@ -6,38 +6,134 @@
 
 use crate::utils::error::Fat32Error;
 
-/// trait pour un dispositif bloc
+/// nature d'un secteur écrit
+/// 
+/// l'ordre des variantes est celui dans lequel un cache différé écrit ses
+/// secteurs : les données, puis la FAT, puis les répertoires et secteurs
+/// réservés qui y font référence.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum SectorKind {
+    Data,
+    Fat,
+    Directory,
+}
+
+/// trait pour un dispositif bloc lisible
+/// 
+/// les numéros de secteur sont absolus sur le dispositif et sur 64 bits,
+/// pour adresser les disques de plus de 2 Tio ; le parser y ajoute
+/// lui-même le début de la partition.
+/// 
+/// un parser construit sur un dispositif qui n'implémente que ce trait
+/// n'expose que les opérations de lecture ; les opérations qui modifient
+/// le volume demandent [`BlockDeviceMut`].
 /// 
 /// # Exemples
 /// 
 /// ```no_run
-/// use fat32_parser::block_device::BlockDevice;
+/// use fat32_parser::block_device::{BlockDevice, BlockDeviceMut};
 /// use fat32_parser::error::Fat32Error;
 /// 
 /// struct MonDevice;
 /// 
 /// impl BlockDevice for MonDevice {
-///     fn read_sector(&self, sector: u32, buffer: &mut [u8]) -> Result<(), Fat32Error> {
+///     type Error = core::convert::Infallible;
+/// 
+///     fn read_sector(&self, sector: u64, buffer: &mut [u8]) -> Result<(), Fat32Error> {
 ///         // lecture du secteur
 ///         Ok(())
 ///     }
-///     
-///     fn write_sector(&mut self, sector: u32, buffer: &[u8]) -> Result<(), Fat32Error> {
+/// }
+/// 
+/// // seulement si le support accepte les écritures
+/// impl BlockDeviceMut for MonDevice {
+///     fn write_sector(&mut self, sector: u64, buffer: &[u8]) -> Result<(), Fat32Error> {
 ///         // écriture du secteur
 ///         Ok(())
 ///     }
 /// }
 /// ```
 pub trait BlockDevice {
+    /// erreur propre au dispositif
+    /// 
+    /// transmise dans `Fat32Error::Device` ; `Infallible` pour un
+    /// dispositif qui ne signale que les erreurs du parser.
+    type Error: core::fmt::Debug;
+    
     /// lit un secteur
-    fn read_sector(&self, sector: u32, buffer: &mut [u8]) -> Result<(), Fat32Error>;
+    fn read_sector(&self, sector: u64, buffer: &mut [u8]) -> Result<(), Fat32Error<Self::Error>>;
     
-    /// écrit un secteur
-    fn write_sector(&mut self, sector: u32, buffer: &[u8]) -> Result<(), Fat32Error>;
+    /// lit plusieurs secteurs consécutifs à partir de `start`
+    /// 
+    /// `buffer` doit faire un multiple de 512 octets. par défaut, lit les
+    /// secteurs un par un ; un dispositif capable de transferts multiblocs
+    /// a intérêt à la redéfinir.
+    fn read_sectors(&self, start: u64, buffer: &mut [u8]) -> Result<(), Fat32Error<Self::Error>> {
+        if !buffer.len().is_multiple_of(512) {
+            return Err(Fat32Error::BufferTooSmall);
+        }
+        for (i, chunk) in buffer.chunks_exact_mut(512).enumerate() {
+            self.read_sector(start + i as u64, chunk)?;
+        }
+        Ok(())
+    }
     
     /// retourne la taille d'un secteur
     fn sector_size(&self) -> u32 {
         512
     }
+    
+    /// retourne le nombre de secteurs du dispositif, s'il est connu
+    /// 
+    /// au montage, le parser compare cette taille à celle annoncée par le
+    /// boot sector pour repérer une image tronquée. par défaut `None` :
+    /// aucune vérification.
+    fn sector_count(&self) -> Option<u64> {
+        None
+    }
 }
 
+/// trait pour un dispositif bloc accessible en écriture
+/// 
+/// les opérations d'écriture du parser exigent ce trait ; elles sont
+/// refusées à la compilation sur un dispositif en lecture seule :
+/// 
+/// ```compile_fail
+/// use fat32_parser::block_device::BlockDevice;
+/// use fat32_parser::parser::Fat32Parser;
+/// 
+/// fn effacer<D: BlockDevice>(parser: &mut Fat32Parser<D>) {
+///     let _ = parser.free_cluster_chain(3);
+/// }
+/// ```
+pub trait BlockDeviceMut: BlockDevice {
+    /// écrit un secteur
+    fn write_sector(&mut self, sector: u64, buffer: &[u8]) -> Result<(), Fat32Error<Self::Error>>;
+    
+    /// écrit plusieurs secteurs consécutifs à partir de `start`
+    /// 
+    /// `buffer` doit faire un multiple de 512 octets. par défaut, écrit les
+    /// secteurs un par un.
+    fn write_sectors(&mut self, start: u64, buffer: &[u8]) -> Result<(), Fat32Error<Self::Error>> {
+        if !buffer.len().is_multiple_of(512) {
+            return Err(Fat32Error::BufferTooSmall);
+        }
+        for (i, chunk) in buffer.chunks_exact(512).enumerate() {
+            self.write_sector(start + i as u64, chunk)?;
+        }
+        Ok(())
+    }
+    
+    /// écrit un secteur en précisant sa nature
+    /// 
+    /// par défaut équivaut à `write_sector` ; un cache s'en sert pour
+    /// ordonner les écritures différées.
+    fn write_sector_kind(&mut self, sector: u64, buffer: &[u8], _kind: SectorKind) -> Result<(), Fat32Error<Self::Error>> {
+        self.write_sector(sector, buffer)
+    }
+    
+    /// écrit sur le support les données encore en attente
+    fn flush(&mut self) -> Result<(), Fat32Error<Self::Error>> {
+        Ok(())
+    }
+}
@@ -0,0 +1,26 @@
+//! trait pour les stratégies d'allocation de clusters
+//! 
+//! une stratégie choisit où placer les nouveaux clusters ; le parser se
+//! charge ensuite d'écrire la chaîne dans la FAT. les stratégies
+//! fournies sont dans `operations::allocator`.
+
+use crate::operations::parser::Fat32Parser;
+use crate::traits::block_device::BlockDevice;
+use crate::utils::error::Fat32Error;
+
+/// trait pour une stratégie d'allocation
+pub trait ClusterAllocator {
+    /// cherche `length` clusters libres consécutifs
+    /// 
+    /// `previous` est le dernier cluster de la chaîne à prolonger, s'il y
+    /// en a une. retourne le premier cluster de la suite trouvée.
+    fn find_run<D: BlockDevice>(
+        &mut self,
+        parser: &Fat32Parser<D>,
+        previous: Option<u32>,
+        length: u32,
+    ) -> Result<Option<u32>, Fat32Error<D::Error>>;
+    
+    /// appelé une fois la suite écrite dans la FAT
+    fn allocated(&mut self, _first: u32, _length: u32) {}
+}
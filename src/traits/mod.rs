@@ -1,4 +1,6 @@
 //! traits pour le parser FAT32
 
 pub mod block_device;
+pub mod cluster_allocator;
 
+pub mod async_block_device;
@@ -0,0 +1,54 @@
+//! lecture et écriture little-endian dans des tampons d'octets
+//! 
+//! les structures sur disque sont décodées champ par champ avec ces
+//! fonctions, quel que soit l'ordre des octets de la cible. les
+//! appelants vérifient la longueur du tampon au préalable, par exemple
+//! avec `head`.
+
+use crate::utils::error::Fat32Error;
+
+/// lit un `u16` little-endian à `offset`
+pub fn u16_at(data: &[u8], offset: usize) -> u16 {
+    u16::from_le_bytes([data[offset], data[offset + 1]])
+}
+
+/// lit un `u32` little-endian à `offset`
+pub fn u32_at(data: &[u8], offset: usize) -> u32 {
+    u32::from_le_bytes([data[offset], data[offset + 1], data[offset + 2], data[offset + 3]])
+}
+
+/// copie `N` octets à partir de `offset`
+pub fn array_at<const N: usize>(data: &[u8], offset: usize) -> [u8; N] {
+    let mut out = [0u8; N];
+    out.copy_from_slice(&data[offset..offset + N]);
+    out
+}
+
+/// écrit un `u16` little-endian à `offset`
+pub fn put_u16(out: &mut [u8], offset: usize, value: u16) {
+    out[offset..offset + 2].copy_from_slice(&value.to_le_bytes());
+}
+
+/// écrit un `u32` little-endian à `offset`
+pub fn put_u32(out: &mut [u8], offset: usize, value: u32) {
+    out[offset..offset + 4].copy_from_slice(&value.to_le_bytes());
+}
+
+/// copie `bytes` à partir de `offset`
+pub fn put_bytes(out: &mut [u8], offset: usize, bytes: &[u8]) {
+    out[offset..offset + bytes.len()].copy_from_slice(bytes);
+}
+
+/// premiers `N` octets de `data`, ou `BufferTooSmall`
+pub fn head<const N: usize>(data: &[u8]) -> Result<&[u8; N], Fat32Error> {
+    data.get(..N)
+        .and_then(|bytes| bytes.try_into().ok())
+        .ok_or(Fat32Error::BufferTooSmall)
+}
+
+/// premiers `N` octets de `out` en écriture, ou `BufferTooSmall`
+pub fn head_mut<const N: usize>(out: &mut [u8]) -> Result<&mut [u8; N], Fat32Error> {
+    out.get_mut(..N)
+        .and_then(|bytes| bytes.try_into().ok())
+        .ok_or(Fat32Error::BufferTooSmall)
+}
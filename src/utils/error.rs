@@ -1,29 +1,173 @@
 //! types d'erreurs
 //! 
 //! définit les différents types d'erreurs qui peuvent survenir
-//! lors de l'utilisation du parser FAT32.
+//! lors de l'utilisation du parser FAT32. chaque erreur porte le secteur
+//! ou le cluster concerné quand il est connu, et se range dans une
+//! [`ErrorCategory`] : dispositif, corruption du volume ou usage.
+//! 
+//! le paramètre `E` est l'erreur propre au dispositif, transmise telle
+//! quelle dans [`Fat32Error::Device`] ; il vaut `Infallible` pour les
+//! erreurs qui ne touchent pas au dispositif (analyse d'une structure
+//! en mémoire par exemple).
+//! 
+//! les erreurs ne portent pas de chemin : le parser désigne fichiers et
+//! répertoires par leur cluster ou l'emplacement de leur entrée, jamais
+//! par un chemin, et en stocker un imposerait `alloc` et ferait perdre
+//! `Copy`. l'appelant qui connaît le chemin l'ajoute au message, comme le
+//! fait la ligne de commande.
+
+use core::convert::Infallible;
+use core::fmt;
+use crate::utils::validator::Problem;
+
+/// famille d'une erreur
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorCategory {
+    /// le dispositif a échoué ou a refusé l'opération
+    Device,
+    /// le volume est incohérent
+    Corruption,
+    /// la demande est invalide pour ce volume
+    Usage,
+}
 
 /// erreurs du parser FAT32
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
-pub enum Fat32Error {
-    /// signature invalide dans le boot sector ou FSInfo
-    InvalidSignature,
-    /// numéro de secteur invalide
-    InvalidSector,
-    /// numéro de cluster invalide
-    InvalidCluster,
+pub enum Fat32Error<E = Infallible> {
+    /// erreur propre au dispositif
+    Device { sector: u64, error: E },
     /// erreur de lecture
-    ReadError,
+    ReadError { sector: u64 },
     /// erreur d'écriture
-    WriteError,
+    WriteError { sector: u64 },
+    /// secteur hors du dispositif
+    InvalidSector { sector: u64 },
+    /// dispositif ou volume en lecture seule
+    ReadOnly,
+    /// signature invalide dans le boot sector, FSInfo ou le journal
+    InvalidSignature { sector: u64 },
+    /// boot sector refusé au montage, voir `validator::validate`
+    InvalidBootSector { problem: Problem },
+    /// cluster hors du volume
+    InvalidCluster { cluster: u32 },
+    /// chaîne de clusters incohérente (cluster libre ou réservé, boucle)
+    BadChain { cluster: u32 },
+    /// entrée de répertoire incohérente
+    InvalidEntry { sector: u64, index: usize },
     /// élément non trouvé
     NotFound,
+    /// l'entrée n'est pas un répertoire
+    NotADirectory,
     /// disque plein
     DiskFull,
     /// élément existe déjà
     AlreadyExists,
+    /// nom de fichier invalide
+    InvalidName,
+    /// nom trop long pour les entrées LFN
+    NameTooLong { length: usize },
+    /// buffer fourni trop petit
+    BufferTooSmall,
 }
 
-/// type résultat pour les opérations FAT32
-pub type Result<T> = core::result::Result<T, Fat32Error>;
+impl<E> Fat32Error<E> {
+    /// retourne la famille de l'erreur
+    pub fn category(&self) -> ErrorCategory {
+        match self {
+            Fat32Error::Device { .. }
+            | Fat32Error::ReadError { .. }
+            | Fat32Error::WriteError { .. }
+            | Fat32Error::InvalidSector { .. }
+            | Fat32Error::ReadOnly => ErrorCategory::Device,
+            Fat32Error::InvalidSignature { .. }
+            | Fat32Error::InvalidBootSector { .. }
+            | Fat32Error::InvalidCluster { .. }
+            | Fat32Error::BadChain { .. }
+            | Fat32Error::InvalidEntry { .. } => ErrorCategory::Corruption,
+            _ => ErrorCategory::Usage,
+        }
+    }
+    
+    /// retourne le secteur concerné, s'il est connu
+    pub fn sector(&self) -> Option<u64> {
+        match *self {
+            Fat32Error::Device { sector, .. }
+            | Fat32Error::ReadError { sector }
+            | Fat32Error::WriteError { sector }
+            | Fat32Error::InvalidSector { sector }
+            | Fat32Error::InvalidSignature { sector }
+            | Fat32Error::InvalidEntry { sector, .. } => Some(sector),
+            _ => None,
+        }
+    }
+    
+    /// retourne le cluster concerné, s'il est connu
+    pub fn cluster(&self) -> Option<u32> {
+        match *self {
+            Fat32Error::InvalidCluster { cluster } | Fat32Error::BadChain { cluster } => Some(cluster),
+            _ => None,
+        }
+    }
+    
+    /// transforme l'erreur du dispositif
+    pub fn map_device<F>(self, f: impl FnOnce(E) -> F) -> Fat32Error<F> {
+        match self {
+            Fat32Error::Device { sector, error } => Fat32Error::Device { sector, error: f(error) },
+            Fat32Error::ReadError { sector } => Fat32Error::ReadError { sector },
+            Fat32Error::WriteError { sector } => Fat32Error::WriteError { sector },
+            Fat32Error::InvalidSector { sector } => Fat32Error::InvalidSector { sector },
+            Fat32Error::ReadOnly => Fat32Error::ReadOnly,
+            Fat32Error::InvalidSignature { sector } => Fat32Error::InvalidSignature { sector },
+            Fat32Error::InvalidBootSector { problem } => Fat32Error::InvalidBootSector { problem },
+            Fat32Error::InvalidCluster { cluster } => Fat32Error::InvalidCluster { cluster },
+            Fat32Error::BadChain { cluster } => Fat32Error::BadChain { cluster },
+            Fat32Error::InvalidEntry { sector, index } => Fat32Error::InvalidEntry { sector, index },
+            Fat32Error::NotFound => Fat32Error::NotFound,
+            Fat32Error::NotADirectory => Fat32Error::NotADirectory,
+            Fat32Error::DiskFull => Fat32Error::DiskFull,
+            Fat32Error::AlreadyExists => Fat32Error::AlreadyExists,
+            Fat32Error::InvalidName => Fat32Error::InvalidName,
+            Fat32Error::NameTooLong { length } => Fat32Error::NameTooLong { length },
+            Fat32Error::BufferTooSmall => Fat32Error::BufferTooSmall,
+        }
+    }
+}
+
+impl Fat32Error {
+    /// convertit une erreur sans dispositif vers le type d'un dispositif
+    pub fn widen<E>(self) -> Fat32Error<E> {
+        self.map_device(|never| match never {})
+    }
+}
 
+impl<E: fmt::Display> fmt::Display for Fat32Error<E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Fat32Error::Device { sector, error } => write!(f, "erreur du dispositif au secteur {}: {}", sector, error),
+            Fat32Error::ReadError { sector } => write!(f, "erreur de lecture au secteur {}", sector),
+            Fat32Error::WriteError { sector } => write!(f, "erreur d'écriture au secteur {}", sector),
+            Fat32Error::InvalidSector { sector } => write!(f, "secteur {} hors du dispositif", sector),
+            Fat32Error::ReadOnly => f.write_str("dispositif en lecture seule"),
+            Fat32Error::InvalidSignature { sector } => write!(f, "signature invalide au secteur {}", sector),
+            Fat32Error::InvalidBootSector { problem } => write!(f, "boot sector refusé: {}", problem),
+            Fat32Error::InvalidCluster { cluster } => write!(f, "cluster {} hors du volume", cluster),
+            Fat32Error::BadChain { cluster } => write!(f, "chaîne de clusters incohérente au cluster {}", cluster),
+            Fat32Error::InvalidEntry { sector, index } => {
+                write!(f, "entrée {} du secteur {} incohérente", index, sector)
+            }
+            Fat32Error::NotFound => f.write_str("élément non trouvé"),
+            Fat32Error::NotADirectory => f.write_str("l'entrée n'est pas un répertoire"),
+            Fat32Error::DiskFull => f.write_str("disque plein"),
+            Fat32Error::AlreadyExists => f.write_str("l'élément existe déjà"),
+            Fat32Error::InvalidName => f.write_str("nom de fichier invalide"),
+            Fat32Error::NameTooLong { length } => write!(f, "nom trop long ({} caractères)", length),
+            Fat32Error::BufferTooSmall => f.write_str("buffer trop petit"),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl<E: fmt::Debug + fmt::Display> std::error::Error for Fat32Error<E> {}
+
+/// type résultat pour les opérations FAT32
+pub type Result<T, E = Infallible> = core::result::Result<T, Fat32Error<E>>;
@@ -31,3 +31,17 @@ pub fn mask_cluster(cluster: u32) -> u32 {
     cluster & FAT_MASK
 }
 
+/// décode une entrée dans un secteur de FAT
+pub fn read_entry(sector: &[u8], offset: usize) -> u32 {
+    mask_cluster(u32::from_le_bytes([
+        sector[offset],
+        sector[offset + 1],
+        sector[offset + 2],
+        sector[offset + 3],
+    ]))
+}
+
+/// encode une entrée dans un secteur de FAT
+pub fn write_entry(sector: &mut [u8], offset: usize, value: u32) {
+    sector[offset..offset + 4].copy_from_slice(&mask_cluster(value).to_le_bytes());
+}
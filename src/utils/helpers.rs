@@ -6,9 +6,9 @@ pub fn short_name_to_string(name: &[u8; 11]) -> [u8; 12] {
     let mut pos = 0;
     
     // partie nom (8 caractères)
-    for i in 0..8 {
-        if name[i] != b' ' {
-            result[pos] = name[i];
+    for &byte in &name[..8] {
+        if byte != b' ' {
+            result[pos] = byte;
             pos += 1;
         } else {
             break;
@@ -22,9 +22,9 @@ pub fn short_name_to_string(name: &[u8; 11]) -> [u8; 12] {
         result[pos] = b'.';
         pos += 1;
         
-        for i in 8..11 {
-            if name[i] != b' ' {
-                result[pos] = name[i];
+        for &byte in &name[8..11] {
+            if byte != b' ' {
+                result[pos] = byte;
                 pos += 1;
             }
         }
@@ -33,6 +33,25 @@ pub fn short_name_to_string(name: &[u8; 11]) -> [u8; 12] {
     result
 }
 
+/// convertit un nom court FAT en `String` (« NOM.EXT »)
+#[cfg(feature = "alloc")]
+pub fn short_name_string(name: &[u8; 11]) -> alloc::string::String {
+    let bytes = short_name_to_string(name);
+    let len = bytes.iter().position(|&b| b == 0).unwrap_or(bytes.len());
+    bytes[..len].iter().map(|&b| b as char).collect()
+}
+
+/// valeur initiale de [`fnv1a`]
+pub(crate) const FNV1A_INIT: u32 = 0x811C9DC5;
+
+/// ajoute `bytes` à un hash FNV-1a
+pub(crate) fn fnv1a(mut hash: u32, bytes: &[u8]) -> u32 {
+    for &b in bytes {
+        hash = (hash ^ b as u32).wrapping_mul(0x01000193);
+    }
+    hash
+}
+
 /// calcule un checksum pour les entrées LFN
 pub fn lfn_checksum(short_name: &[u8; 11]) -> u8 {
     let mut sum: u8 = 0;
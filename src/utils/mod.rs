@@ -6,3 +6,4 @@ pub mod fat;
 pub mod helpers;
 pub mod validator;
 
+pub mod bytes;
@@ -1,61 +1,318 @@
 //! fonctions de validation
+//! 
+//! la validation du boot sector relève tous les problèmes trouvés, chacun
+//! avec sa gravité ; `MountOptions` choisit ensuite lesquels empêchent le
+//! montage.
 
 use crate::structures::boot_sector::BootSector;
+use crate::structures::geometry::FatType;
 use crate::utils::constants::*;
+use crate::utils::error::Fat32Error;
 
-/// valide un boot sector FAT32
-pub fn validate_boot_sector(bs: &BootSector) -> bool {
-    // vérifier signature
-    if !bs.is_valid() {
-        return false;
+/// nombre maximal de problèmes relevés par un rapport
+pub const MAX_PROBLEMS: usize = 16;
+
+/// gravité d'un problème
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Severity {
+    /// inhabituel mais sans conséquence pour le parser
+    Warning,
+    /// volume incohérent, lisible en mode tolérant
+    Error,
+    /// volume inutilisable, même en mode tolérant
+    Fatal,
+}
+
+/// problème relevé dans un boot sector
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Problem {
+    /// signature 0xAA55 absente
+    InvalidSignature(u16),
+    /// taille de secteur autre que 512 octets
+    BytesPerSector(u16),
+    /// secteurs par cluster nul ou pas une puissance de deux
+    SectorsPerCluster(u8),
+    /// clusters de plus de 32 Kio, refusés par certains systèmes
+    LargeClusters(u32),
+    /// aucun secteur réservé
+    NoReservedSectors,
+    /// nombre de FAT nul
+    NoFat,
+    /// plus de deux FAT
+    ManyFats(u8),
+    /// champs propres à FAT12/16 non nuls
+    NotFat32,
+    /// taille de FAT nulle
+    ZeroFatSize,
+    /// FAT trop petite pour le nombre de clusters
+    FatTooSmall { needed: u32, actual: u32 },
+    /// FAT et secteurs réservés dépassent la taille du volume
+    NoDataRegion,
+    /// moins de 65525 clusters, taille d'un volume FAT16
+    FewClusters(u32),
+    /// cluster racine hors de la zone de données
+    RootClusterOutOfRange(u32),
+    /// secteur FSInfo hors des secteurs réservés
+    FsInfoOutOfRange(u16),
+    /// copie du boot sector hors des secteurs réservés
+    BackupOutOfRange(u16),
+    /// volume plus grand que le dispositif
+    BeyondDevice { volume: u64, device: u64 },
+    /// version du système de fichiers non prise en charge
+    UnsupportedVersion(u16),
+}
+
+impl Problem {
+    /// retourne la gravité du problème
+    pub fn severity(&self) -> Severity {
+        match self {
+            Problem::InvalidSignature(_)
+            | Problem::BytesPerSector(_)
+            | Problem::SectorsPerCluster(_)
+            | Problem::NoReservedSectors
+            | Problem::NoFat
+            | Problem::ZeroFatSize
+            | Problem::NoDataRegion
+            | Problem::RootClusterOutOfRange(_) => Severity::Fatal,
+            Problem::NotFat32
+            | Problem::FatTooSmall { .. }
+            | Problem::BeyondDevice { .. }
+            | Problem::UnsupportedVersion(_) => Severity::Error,
+            Problem::LargeClusters(_)
+            | Problem::ManyFats(_)
+            | Problem::FewClusters(_)
+            | Problem::FsInfoOutOfRange(_)
+            | Problem::BackupOutOfRange(_) => Severity::Warning,
+        }
+    }
+}
+
+impl core::fmt::Display for Problem {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match *self {
+            Problem::InvalidSignature(found) => write!(f, "signature {:#06x} au lieu de 0xaa55", found),
+            Problem::BytesPerSector(n) => write!(f, "{} octets par secteur (512 attendus)", n),
+            Problem::SectorsPerCluster(n) => write!(f, "{} secteurs par cluster", n),
+            Problem::LargeClusters(size) => write!(f, "clusters de {} octets", size),
+            Problem::NoReservedSectors => f.write_str("aucun secteur réservé"),
+            Problem::NoFat => f.write_str("aucune FAT"),
+            Problem::ManyFats(n) => write!(f, "{} FAT", n),
+            Problem::NotFat32 => f.write_str("champs FAT12/16 renseignés"),
+            Problem::ZeroFatSize => f.write_str("taille de FAT nulle"),
+            Problem::FatTooSmall { needed, actual } => {
+                write!(f, "FAT de {} secteurs, {} nécessaires", actual, needed)
+            }
+            Problem::NoDataRegion => f.write_str("pas de zone de données"),
+            Problem::FewClusters(n) => write!(f, "seulement {} clusters", n),
+            Problem::RootClusterOutOfRange(cluster) => write!(f, "cluster racine {} hors du volume", cluster),
+            Problem::FsInfoOutOfRange(sector) => write!(f, "FSInfo au secteur {} hors des secteurs réservés", sector),
+            Problem::BackupOutOfRange(sector) => {
+                write!(f, "copie du boot sector au secteur {} hors des secteurs réservés", sector)
+            }
+            Problem::BeyondDevice { volume, device } => {
+                write!(f, "volume de {} secteurs sur un dispositif de {}", volume, device)
+            }
+            Problem::UnsupportedVersion(version) => write!(f, "version {:#06x} non prise en charge", version),
+        }
+    }
+}
+
+/// problèmes relevés par `validate`
+#[derive(Debug, Clone, Copy)]
+pub struct ValidationReport {
+    problems: [Problem; MAX_PROBLEMS],
+    count: usize,
+}
+
+impl ValidationReport {
+    fn new() -> Self {
+        Self {
+            problems: [Problem::NoFat; MAX_PROBLEMS],
+            count: 0,
+        }
     }
     
-    // vérifier bytes par secteur (doit être 512)
-    if bs.bytes_per_sector != SECTOR_SIZE as u16 {
-        return false;
+    fn push(&mut self, problem: Problem) {
+        if self.count < MAX_PROBLEMS {
+            self.problems[self.count] = problem;
+            self.count += 1;
+        }
     }
     
-    // vérifier secteurs par cluster (doit être puissance de 2)
-    let spc = bs.sectors_per_cluster;
-    if spc == 0 || (spc & (spc - 1)) != 0 {
-        return false;
+    /// retourne les problèmes dans l'ordre où ils ont été trouvés
+    pub fn problems(&self) -> &[Problem] {
+        &self.problems[..self.count]
     }
     
-    // vérifier nombre de FAT (généralement 2)
-    if bs.num_fats == 0 || bs.num_fats > 2 {
-        return false;
+    /// retourne `true` si aucun problème n'a été relevé
+    pub fn is_clean(&self) -> bool {
+        self.count == 0
     }
     
-    // pour FAT32, root_entry_count doit être 0
-    if bs.root_entry_count != 0 {
-        return false;
+    /// retourne la gravité du problème le plus grave
+    pub fn worst(&self) -> Option<Severity> {
+        self.problems().iter().map(Problem::severity).max()
     }
     
-    // pour FAT32, total_sectors_16 doit être 0
-    if bs.total_sectors_16 != 0 {
-        return false;
+    /// retourne le premier problème au moins aussi grave que `severity`
+    pub fn first_at_least(&self, severity: Severity) -> Option<Problem> {
+        self.problems().iter().copied().find(|p| p.severity() >= severity)
+    }
+}
+
+/// traitement des problèmes au montage
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Strictness {
+    /// refuse le volume dès qu'un problème de gravité `Error` est relevé
+    Strict,
+    /// ne refuse que les problèmes `Fatal`
+    Lenient,
+}
+
+/// options de montage
+#[derive(Debug, Clone, Copy)]
+pub struct MountOptions {
+    /// traitement des problèmes du boot sector
+    pub strictness: Strictness,
+    /// taille du dispositif en secteurs à partir du début du volume ; si
+    /// `None`, celle que donne `BlockDevice::sector_count`
+    pub device_sectors: Option<u64>,
+}
+
+impl Default for MountOptions {
+    fn default() -> Self {
+        Self {
+            strictness: Strictness::Strict,
+            device_sectors: None,
+        }
+    }
+}
+
+impl MountOptions {
+    /// options tolérantes, pour examiner un volume abîmé
+    pub fn lenient() -> Self {
+        Self {
+            strictness: Strictness::Lenient,
+            ..Self::default()
+        }
     }
     
-    // pour FAT32, fat_size_16 doit être 0
-    if bs.fat_size_16 != 0 {
-        return false;
+    /// retourne le problème qui empêche le montage, s'il y en a un
+    pub fn rejects(&self, report: &ValidationReport) -> Option<Problem> {
+        match self.strictness {
+            Strictness::Strict => report.first_at_least(Severity::Error),
+            Strictness::Lenient => report.first_at_least(Severity::Fatal),
+        }
     }
     
-    // vérifier que fat_size_32 est non nul
+    /// complète `device_sectors` avec la taille du dispositif, s'il n'a
+    /// pas été fixé
+    pub(crate) fn with_device_size(self, sector_count: Option<u64>, partition_start: u64) -> Self {
+        Self {
+            device_sectors: self.device_sectors.or(sector_count.map(|n| n.saturating_sub(partition_start))),
+            ..self
+        }
+    }
+    
+    /// valide le boot sector lu au secteur `sector` du dispositif
+    /// 
+    /// retourne le rapport si le volume peut être monté ; sinon
+    /// `InvalidSignature` ou `InvalidBootSector` avec le problème en cause.
+    pub(crate) fn check<E>(&self, bs: &BootSector, sector: u64) -> Result<ValidationReport, Fat32Error<E>> {
+        let report = validate(bs, self.device_sectors);
+        match self.rejects(&report) {
+            None => Ok(report),
+            Some(Problem::InvalidSignature(_)) => Err(Fat32Error::InvalidSignature { sector }),
+            Some(problem) => Err(Fat32Error::InvalidBootSector { problem }),
+        }
+    }
+}
+
+/// valide un boot sector FAT32
+/// 
+/// `device_sectors` est le nombre de secteurs disponibles sur le dispositif
+/// à partir du début du volume, s'il est connu.
+pub fn validate(bs: &BootSector, device_sectors: Option<u64>) -> ValidationReport {
+    let mut report = ValidationReport::new();
+    
+    if !bs.is_valid() {
+        report.push(Problem::InvalidSignature(bs.signature));
+    }
+    if bs.bytes_per_sector != SECTOR_SIZE as u16 {
+        report.push(Problem::BytesPerSector(bs.bytes_per_sector));
+    }
+    
+    let spc = bs.sectors_per_cluster;
+    if !spc.is_power_of_two() {
+        report.push(Problem::SectorsPerCluster(spc));
+    } else if bs.cluster_size() > 32 * 1024 {
+        report.push(Problem::LargeClusters(bs.cluster_size()));
+    }
+    
+    if bs.reserved_sector_count == 0 {
+        report.push(Problem::NoReservedSectors);
+    }
+    match bs.num_fats {
+        0 => report.push(Problem::NoFat),
+        1 | 2 => {}
+        n => report.push(Problem::ManyFats(n)),
+    }
+    if bs.root_entry_count != 0 || bs.total_sectors_16 != 0 || bs.fat_size_16 != 0 {
+        report.push(Problem::NotFat32);
+    }
     if bs.fat_size_32 == 0 {
-        return false;
+        report.push(Problem::ZeroFatSize);
+    }
+    if bs.fs_version != 0 {
+        report.push(Problem::UnsupportedVersion(bs.fs_version));
+    }
+    
+    // géométrie : seulement si les champs de base sont exploitables
+    let geometry = bs.geometry();
+    if spc.is_power_of_two() && bs.fat_size_32 != 0 {
+        if geometry.cluster_count == 0 {
+            report.push(Problem::NoDataRegion);
+        } else {
+            let needed = geometry.fat_sectors_needed();
+            if bs.fat_size_32 < needed {
+                report.push(Problem::FatTooSmall { needed, actual: bs.fat_size_32 });
+            }
+            if geometry.fat_type() != FatType::Fat32 {
+                report.push(Problem::FewClusters(geometry.cluster_count));
+            }
+            if !geometry.contains_cluster(bs.root_cluster) {
+                report.push(Problem::RootClusterOutOfRange(bs.root_cluster));
+            }
+        }
+    }
+    
+    let reserved = bs.reserved_sector_count;
+    if bs.fs_info_sector != 0 && bs.fs_info_sector != 0xFFFF && bs.fs_info_sector >= reserved {
+        report.push(Problem::FsInfoOutOfRange(bs.fs_info_sector));
+    }
+    if bs.backup_boot_sector != 0 && bs.backup_boot_sector != 0xFFFF && bs.backup_boot_sector >= reserved {
+        report.push(Problem::BackupOutOfRange(bs.backup_boot_sector));
     }
     
-    // cluster racine doit être >= 2
-    if bs.root_cluster < FIRST_VALID_CLUSTER {
-        return false;
+    let total = bs.total_sectors() as u64;
+    if let Some(device) = device_sectors {
+        if total > device {
+            report.push(Problem::BeyondDevice { volume: total, device });
+        }
     }
     
-    true
+    report
+}
+
+/// valide un boot sector FAT32
+/// 
+/// retourne `true` si aucun problème de gravité `Error` ou `Fatal` n'est
+/// relevé ; voir `validate` pour le détail.
+pub fn validate_boot_sector(bs: &BootSector) -> bool {
+    validate(bs, None).worst().is_none_or(|worst| worst < Severity::Error)
 }
 
 /// vérifie si un cluster est valide
 pub fn is_valid_cluster(cluster: u32) -> bool {
-    cluster >= FIRST_VALID_CLUSTER && cluster < 0x0FFFFFF8
+    (FIRST_VALID_CLUSTER..0x0FFFFFF8).contains(&cluster)
 }
-